@@ -2,6 +2,14 @@ from flask import Flask, render_template
 
 app = Flask(__name__)
 
+# Cross-origin isolation (SharedArrayBuffer, full-resolution timers). credentialless
+# rather than require-corp, so the cross-origin audio stream still loads.
+@app.after_request
+def cross_origin_isolation(response):
+    response.headers["Cross-Origin-Opener-Policy"] = "same-origin"
+    response.headers["Cross-Origin-Embedder-Policy"] = "credentialless"
+    return response
+
 # @app.route("/")
 # def alpine():
 #     return render_template("alpine.html", title="Alpine Visualizer")
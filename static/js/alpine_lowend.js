@@ -3,6 +3,10 @@ const SCREEN_TEXT = 'SILENTWAVE F1 87.9'
 const TARGET_FPS = 0
 let RENDER_SCALE = 1
 let FFT_SIZE = 256
+// Work budget per frame (ms) the adaptive quality governor aims for from the
+// start; 0 leaves it off until setAutoQuality(true), which defaults to 12 ms
+const AUTO_QUALITY_MS = 0
+const AUTO_QUALITY_DEFAULT_MS = 12
 const MEDIA_AUDIO = {
   echoCancellation: false,
   noiseSuppression: false,
@@ -47,6 +51,23 @@ function resizeCanvas() {
   }
 }
 
+// Feeds the pointer to the renderer in canvas pixels: hovering steers the tunnel,
+// holding the button on the canvas reverses it. Leaving the window lets it drift back.
+function setupPointerSteering() {
+  let down = false
+  const send = (e, active) => {
+    const rect = canvas.getBoundingClientRect()
+    const x = (e.clientX - rect.left) * (canvas.width / rect.width)
+    const y = (e.clientY - rect.top) * (canvas.height / rect.height)
+    const inside = e.clientX >= rect.left && e.clientX < rect.right && e.clientY >= rect.top && e.clientY < rect.bottom
+    wasm.wasm_update_mouse(x, y, active && inside, down)
+  }
+  window.addEventListener('pointermove', (e) => send(e, true), false)
+  canvas.addEventListener('pointerdown', (e) => { down = true; send(e, true) }, false)
+  window.addEventListener('pointerup', (e) => { down = false; send(e, true) }, false)
+  document.documentElement.addEventListener('pointerleave', (e) => { down = false; send(e, false) }, false)
+}
+
 function setupTextCells(text = SCREEN_TEXT) {
   const cells = document.getElementById('textCells')
   if (!cells) return
@@ -137,6 +158,7 @@ function setupAnalyserFromStream(stream) {
   analyser.fftSize = FFT_SIZE
   src.connect(analyser)
   fftBuf = new Uint8Array(analyser.frequencyBinCount)
+  if (wasm.set_sample_rate) { wasm.set_sample_rate(ac.sampleRate) }
   if (ac.state === 'suspended') { ac.resume && ac.resume() }
   hasAudioSource = true
 }
@@ -158,6 +180,7 @@ function setupFileAnalyser(file) {
   src.connect(analyser)
   analyser.connect(ac.destination)
   fftBuf = new Uint8Array(analyser.frequencyBinCount)
+  if (wasm.set_sample_rate) { wasm.set_sample_rate(ac.sampleRate) }
   if (ac.state === 'suspended') { ac.resume && ac.resume() }
   hasAudioSource = true
   currentAudio = audio
@@ -173,16 +196,46 @@ async function start() {
   window.addEventListener('resize', resizeCanvas, false);
   resizeCanvas();
   wasm.wasm_update_mouse(canvas.width / 2, canvas.height / 2, false, false);
+  setupPointerSteering();
 
   // Expose wasm for console
   window.wasm = wasm;
   window.setPerf = (n) => { try { wasm.set_performance_mode && wasm.set_performance_mode(n); } catch (_) { } };
-  window.setRenderScale = (s) => { try { RENDER_SCALE = Math.max(0.25, Math.min(1.0, Number(s) || 1)); resizeCanvas(); } catch (_) { } };
+  // Presets as JSON text: copy exportPreset() output into a file, pass it back to importPreset()
+  window.exportPreset = () => (wasm.export_preset ? wasm.export_preset() : null);
+  window.importPreset = (text) => { wasm.import_preset && wasm.import_preset(text); };
+  // Prefer the renderer's internal scaling: the canvas stays full size and wasm upscales.
+  // Older builds fall back to shrinking the canvas and letting CSS upscale it.
+  window.setRenderScale = (s) => {
+    try {
+      const scale = Math.max(0.25, Math.min(1.0, Number(s) || 1));
+      if (wasm.set_render_scale) { wasm.set_render_scale(scale); } else { RENDER_SCALE = scale; resizeCanvas(); }
+    } catch (_) { }
+  };
+  window.setUpscale = (name) => { try { wasm.set_upscale_filter && wasm.set_upscale_filter(name); } catch (_) { } };
+  window.setScene = (name) => { try { wasm.set_scene && wasm.set_scene(name); } catch (_) { } };
+  window.setPalette = (spec) => { try { wasm.set_palette && wasm.set_palette(String(spec)); } catch (_) { } };
+  window.setPaletteMode = (name) => { try { wasm.set_palette_mode && wasm.set_palette_mode(name); } catch (_) { } };
+  window.setLineMode = (name) => { try { wasm.set_line_mode && wasm.set_line_mode(name); } catch (_) { } };
+  window.setLineCap = (name) => { try { wasm.set_line_cap && wasm.set_line_cap(name); } catch (_) { } };
+  window.setFaceFill = (name) => { try { wasm.set_face_fill && wasm.set_face_fill(name); } catch (_) { } };
+  window.setDepthTest = (on) => { try { wasm.set_depth_test && wasm.set_depth_test(!!on); } catch (_) { } };
+  window.postPasses = () => { try { return wasm.post_passes ? wasm.post_passes() : []; } catch (_) { return []; } };
+  window.addPostPass = (index, name) => { try { wasm.add_post_pass && wasm.add_post_pass(index, name); } catch (_) { } };
+  window.removePostPass = (index) => { try { wasm.remove_post_pass && wasm.remove_post_pass(index); } catch (_) { } };
+  window.movePostPass = (from, to) => { try { wasm.move_post_pass && wasm.move_post_pass(from, to); } catch (_) { } };
+  window.setPostPassEnabled = (index, on) => { try { wasm.set_post_pass_enabled && wasm.set_post_pass_enabled(index, !!on); } catch (_) { } };
+  window.setPostParam = (index, name, value) => { try { wasm.set_post_param && wasm.set_post_param(index, name, Number(value)); } catch (_) { } };
+  window.setSeed = (n) => { try { wasm.set_seed && wasm.set_seed(BigInt(n)); } catch (_) { } };
 
   // Set performance mode based on device capabilities
   if (wasm.set_performance_mode) {
     wasm.set_performance_mode(2);
   }
+  if (wasm.set_auto_quality && AUTO_QUALITY_MS > 0) {
+    try { wasm.set_auto_quality(true, AUTO_QUALITY_MS); } catch (_) { }
+  }
+  window.setAutoQuality = (on, ms = AUTO_QUALITY_MS || AUTO_QUALITY_DEFAULT_MS) => { try { wasm.set_auto_quality && wasm.set_auto_quality(!!on, ms); } catch (_) { } };
 
   analyser = null;
   fftBuf = new Uint8Array(4096);
@@ -344,6 +397,27 @@ async function start() {
     setupTextCells(SCREEN_TEXT);
   }
 
+  // PRESET 1-4: click recalls a slot, shift+click stores the current look into it
+  if (wasm.recall_preset) {
+    document.querySelectorAll('.presetBtn').forEach((btn) => {
+      btn.addEventListener('click', (e) => {
+        const slot = parseInt(btn.dataset.slot, 10);
+        try {
+          if (e.shiftKey) {
+            wasm.store_preset(slot, `USER ${slot + 1}`);
+          } else {
+            wasm.recall_preset(slot);
+            if (sensitivitySlider && wasm.get_sensitivity) {
+              sensitivitySlider.value = 40.0 - wasm.get_sensitivity() + 5.0;
+            }
+          }
+        } catch (err) {
+          console.warn('preset', err);
+        }
+      }, false);
+    });
+  }
+
   setupTrackInfoFlicker();
 
   const fpsEl = document.getElementById('fpsCounter');
@@ -353,11 +427,13 @@ async function start() {
 }
 
 let last = 0
+let lastRendered = 0
 const targetDt = TARGET_FPS > 0 ? 1000 / TARGET_FPS : 0
 function loop(ts) {
   if (ready) {
     if (TARGET_FPS === 0 || ts - last >= targetDt) {
       last = ts
+      const t0 = performance.now()
       const active = hasAudioSource && analyser && ac && ac.state === 'running'
       if (active) {
         analyser.getByteFrequencyData(fftBuf)
@@ -365,20 +441,40 @@ function loop(ts) {
         fftBuf.fill(0)
       }
       wasm.wasm_update_vu(fftBuf)
-      wasm.wasm_render_frame()
+      // Advance by the real time between frames so speed is the same at any refresh rate
+      if (wasm.wasm_render_frame_dt && lastRendered > 0) { wasm.wasm_render_frame_dt((ts - lastRendered) / 1000) } else { wasm.wasm_render_frame() }
+      lastRendered = ts
       if (pixels && ctx && imageData) { ctx.putImageData(imageData, 0, 0) }
+      if (wasm.report_frame_time) { wasm.report_frame_time(performance.now() - t0) }
+      dispatchDrumHits()
       // update warptunnel fps once per 250ms
       const f = window._alpineFps
       if (f && f.el) {
         f.frames++
         const dt = ts - f.lastT
-        if (dt >= 250) { f.fps = Math.round((f.frames * 1000) / dt); f.frames = 0; f.lastT = ts; f.el.textContent = `WT ${f.fps} FPS` }
+        if (dt >= 250) { f.fps = Math.round((f.frames * 1000) / dt); f.frames = 0; f.lastT = ts; f.el.textContent = `WT ${f.fps} FPS${qualityLabel()}` }
       }
     }
   }
   requestAnimationFrame(loop)
 }
 
+// Re-emits the renderer's kick/snare/hat hits as 'alpine:drum' events on window
+const DRUM_KINDS = ['kick', 'snare', 'hat']
+function dispatchDrumHits() {
+  if (!wasm.take_transients) return
+  const hits = wasm.take_transients()
+  for (let i = 0; i + 2 < hits.length; i += 3) {
+    const detail = { kind: DRUM_KINDS[hits[i]], strength: hits[i + 1], time: hits[i + 2] }
+    window.dispatchEvent(new CustomEvent('alpine:drum', { detail }))
+  }
+}
+
+function qualityLabel() {
+  const name = wasm.quality_name ? wasm.quality_name() : null
+  return name ? ` ${name.toUpperCase()}` : ''
+}
+
 if (document.readyState === 'complete' || document.readyState === 'interactive') {
   setTimeout(start, 0)
 } else {
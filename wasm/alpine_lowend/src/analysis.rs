@@ -0,0 +1,214 @@
+//! Spectrum analysis from raw PCM, producing the 0-255 per-bin layout the
+//! renderer expects (same as `AnalyserNode::getByteFrequencyData`).
+
+use std::f32::consts::TAU;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl Window {
+    pub fn from_name(name: &str) -> Result<Window, String> {
+        match name {
+            "rectangular" => Ok(Window::Rectangular),
+            "hann" => Ok(Window::Hann),
+            "hamming" => Ok(Window::Hamming),
+            "blackman" => Ok(Window::Blackman),
+            _ => Err(format!("unknown window {:?} (expected rectangular, hann, hamming or blackman)", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Window::Rectangular => "rectangular",
+            Window::Hann => "hann",
+            Window::Hamming => "hamming",
+            Window::Blackman => "blackman",
+        }
+    }
+
+    fn coefficients(self, n: usize) -> Vec<f32> {
+        (0..n)
+            .map(|i| {
+                let x = TAU * i as f32 / n as f32;
+                match self {
+                    Window::Rectangular => 1.0,
+                    Window::Hann => 0.5 - 0.5 * x.cos(),
+                    Window::Hamming => 0.54 - 0.46 * x.cos(),
+                    Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalysisConfig {
+    /// Power of two in 32..=32768; yields `fft_size / 2` bins.
+    pub fft_size: usize,
+    pub window: Window,
+    /// Time constant in seconds for a bin rising toward a louder value.
+    pub attack: f32,
+    /// Time constant in seconds for a bin falling toward a quieter value.
+    pub release: f32,
+    /// Level mapped to 0.
+    pub min_db: f32,
+    /// Level mapped to 255.
+    pub max_db: f32,
+}
+
+impl Default for AnalysisConfig {
+    // Matches the page's AnalyserNode: Blackman, smoothingTimeConstant 0.34 at
+    // 60 fps, minDecibels -90, maxDecibels -10
+    fn default() -> AnalysisConfig {
+        AnalysisConfig {
+            fft_size: 256,
+            window: Window::Blackman,
+            attack: 0.0155,
+            release: 0.0155,
+            min_db: -90.0,
+            max_db: -10.0,
+        }
+    }
+}
+
+impl AnalysisConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.fft_size.is_power_of_two() || !(32..=32768).contains(&self.fft_size) {
+            return Err(format!("fft_size must be a power of two in 32..32768, got {}", self.fft_size));
+        }
+        if self.attack.is_nan() || self.release.is_nan() || self.attack < 0.0 || self.release < 0.0 {
+            return Err("attack and release must be non-negative".into());
+        }
+        if !self.min_db.is_finite() || !self.max_db.is_finite() || self.min_db >= self.max_db {
+            return Err(format!("min_db ({}) must be below max_db ({})", self.min_db, self.max_db));
+        }
+        Ok(())
+    }
+}
+
+/// Streaming analyser: push consecutive samples, then read a spectrum.
+/// Smoothing is scaled by the audio time elapsed between reads, so the
+/// response is the same at any frame rate.
+pub struct Analyser {
+    config: AnalysisConfig,
+    sample_rate: f32,
+    window: Vec<f32>,
+    history: Vec<f32>,
+    write_pos: usize,
+    pending: usize,
+    smoothed: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    bytes: Vec<u8>,
+}
+
+impl Analyser {
+    pub fn new(config: AnalysisConfig, sample_rate: f32) -> Result<Analyser, String> {
+        config.validate()?;
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(format!("sample rate must be positive, got {}", sample_rate));
+        }
+        let n = config.fft_size;
+        Ok(Analyser {
+            window: config.window.coefficients(n),
+            config,
+            sample_rate,
+            history: vec![0.0; n],
+            write_pos: 0,
+            pending: 0,
+            smoothed: vec![0.0; n / 2],
+            re: vec![0.0; n],
+            im: vec![0.0; n],
+            bytes: vec![0; n / 2],
+        })
+    }
+
+    pub fn config(&self) -> &AnalysisConfig {
+        &self.config
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Appends mono samples in -1..1; only the latest `fft_size` are kept.
+    pub fn push(&mut self, samples: &[f32]) {
+        let n = self.history.len();
+        let tail = &samples[samples.len().saturating_sub(n)..];
+        for &s in tail {
+            self.history[self.write_pos] = s;
+            self.write_pos = (self.write_pos + 1) % n;
+        }
+        self.pending += samples.len();
+    }
+
+    /// Computes the spectrum of the latest `fft_size` samples.
+    pub fn analyse(&mut self) -> &[u8] {
+        let n = self.config.fft_size;
+        for i in 0..n {
+            self.re[i] = self.history[(self.write_pos + i) % n] * self.window[i];
+            self.im[i] = 0.0;
+        }
+        fft(&mut self.re, &mut self.im);
+
+        let elapsed = self.pending as f32 / self.sample_rate;
+        self.pending = 0;
+        let coeff = |tau: f32| if tau > 0.0 { (-elapsed / tau).exp() } else { 0.0 };
+        let (attack, release) = (coeff(self.config.attack), coeff(self.config.release));
+        let range = self.config.max_db - self.config.min_db;
+        for k in 0..n / 2 {
+            let mag = (self.re[k] * self.re[k] + self.im[k] * self.im[k]).sqrt() / n as f32;
+            let prev = self.smoothed[k];
+            let a = if mag > prev { attack } else { release };
+            let s = prev * a + mag * (1.0 - a);
+            self.smoothed[k] = s;
+            let db = 20.0 * s.max(1e-12).log10();
+            self.bytes[k] = (255.0 / range * (db - self.config.min_db)).clamp(0.0, 255.0) as u8;
+        }
+        &self.bytes
+    }
+}
+
+// In-place iterative radix-2 FFT; len must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let (w_im, w_re) = (-TAU / len as f32).sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0f32, 0.0f32);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+}
@@ -0,0 +1,376 @@
+//! Offline renderer: PCM WAV in, numbered PNG frames or a Y4M stream out.
+//!
+//! Runs the same `Renderer` the browser uses, one spectrum per video frame,
+//! with a fixed seed so re-rendering a track gives identical frames.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use alpine_lowend_wasm::renderer::DEFAULT_SEED;
+use alpine_lowend_wasm::tiles;
+use alpine_lowend_wasm::{AnalysisConfig, Analyser, FaceFill, LineCap, LineMode, Palette, PaletteMode, PostChain, PostKind, Preset, Renderer, UpscaleFilter, Window};
+
+const USAGE: &str = "\
+usage: alpine_render <input.wav> (--png <dir> | --y4m <file|->) [options]
+
+options:
+  --fps <n>            video frame rate (default 30)
+  --size <WxH>         output resolution (default 1280x720)
+  --scale <f>          internal render scale 0.25..1, upscaled to --size (default 1,
+                       overrides the preset)
+  --upscale <name>     nearest or bilinear (default bilinear, overrides the preset)
+  --scene <name>       visual mode (default tunnel, overrides the preset)
+  --lines <name>       aliased, smooth-add or smooth-max (default aliased,
+                       overrides the preset)
+  --caps <name>        round or square ends on wide lines (default round,
+                       overrides the preset)
+  --faces <name>       none, flat or depth shaded solid faces (default none,
+                       overrides the preset)
+  --depth <on|off>     hide lines and faces behind nearer geometry (default off,
+                       overrides the preset)
+  --palette <spec>     gradient for the line colors: sunset, ocean, neon, ember,
+                       ice, mono or #rrggbb stops, e.g. #ff0080,#00e0ff@0.4
+  --palette-mode <m>   sine, time, energy or depth (default sine, or time when
+                       --palette is given; overrides the preset)
+  --threads <n>        worker threads for banded rendering (default: one per
+                       core; needs the threads feature)
+  --post <list>        comma-separated post passes in order, all enabled, e.g.
+                       glow,darken,scanlines (passes: glow, smoothing, darken,
+                       scanlines, invert; default: the classic chain)
+  --fft <n>            analysis FFT size, power of two 32..32768 (default 256)
+  --window <name>      rectangular, hann, hamming or blackman (default blackman)
+  --attack <sec>       spectrum rise time constant (default 0.0155)
+  --release <sec>      spectrum fall time constant (default 0.0155)
+  --min-db <db>        level mapped to 0 (default -90)
+  --max-db <db>        level mapped to 255 (default -10)
+  --preset <file>      preset JSON document to apply before rendering
+  --seed <n>           random seed; the same seed and audio give the same frames
+  --sensitivity <f>    frequency damping, lower is more reactive (default 18,
+                       overrides the preset)
+  --perf <1|2|3>       performance mode (default 2, overrides the preset)";
+
+enum Output {
+    Png(PathBuf),
+    Y4m(String),
+}
+
+struct Options {
+    input: PathBuf,
+    output: Output,
+    fps: u32,
+    width: u32,
+    height: u32,
+    scale: Option<f32>,
+    upscale: Option<UpscaleFilter>,
+    scene: Option<String>,
+    lines: Option<LineMode>,
+    caps: Option<LineCap>,
+    faces: Option<FaceFill>,
+    depth: Option<bool>,
+    palette: Option<Palette>,
+    palette_mode: Option<PaletteMode>,
+    threads: Option<usize>,
+    post: Option<Vec<PostKind>>,
+    analysis: AnalysisConfig,
+    preset: Option<PathBuf>,
+    sensitivity: Option<f32>,
+    performance_mode: Option<u8>,
+    seed: u64,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut input = None;
+    let mut output = None;
+    let mut fps = 30;
+    let (mut width, mut height) = (1280, 720);
+    let mut scale = None;
+    let mut upscale = None;
+    let mut scene = None;
+    let mut lines = None;
+    let mut caps = None;
+    let mut faces = None;
+    let mut depth = None;
+    let mut palette = None;
+    let mut palette_mode = None;
+    let mut threads = None;
+    let mut post = None;
+    let mut analysis = AnalysisConfig::default();
+    let mut preset = None;
+    let mut sensitivity = None;
+    let mut performance_mode = None;
+    let mut seed = DEFAULT_SEED;
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = |name: &str| it.next().cloned().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--png" => output = Some(Output::Png(PathBuf::from(value("--png")?))),
+            "--y4m" => output = Some(Output::Y4m(value("--y4m")?)),
+            "--fps" => fps = parse_num(&value("--fps")?, "--fps")?,
+            "--size" => {
+                let v = value("--size")?;
+                let (w, h) = v.split_once('x').ok_or_else(|| format!("--size expects WxH, got {}", v))?;
+                width = parse_num(w, "--size")?;
+                height = parse_num(h, "--size")?;
+            }
+            "--scale" => scale = Some(parse_num(&value("--scale")?, "--scale")?),
+            "--upscale" => upscale = Some(UpscaleFilter::from_name(&value("--upscale")?)?),
+            "--scene" => scene = Some(value("--scene")?),
+            "--lines" => lines = Some(LineMode::from_name(&value("--lines")?)?),
+            "--caps" => caps = Some(LineCap::from_name(&value("--caps")?)?),
+            "--faces" => faces = Some(FaceFill::from_name(&value("--faces")?)?),
+            "--depth" => {
+                depth = Some(match value("--depth")?.as_str() {
+                    "on" => true,
+                    "off" => false,
+                    v => return Err(format!("--depth expects on or off, got {}", v)),
+                })
+            }
+            "--palette" => palette = Some(Palette::parse(&value("--palette")?)?),
+            "--palette-mode" => palette_mode = Some(PaletteMode::from_name(&value("--palette-mode")?)?),
+            "--threads" => threads = Some(parse_num(&value("--threads")?, "--threads")?),
+            "--post" => {
+                let list = value("--post")?;
+                let kinds = list.split(',').filter(|s| !s.is_empty()).map(PostKind::from_name).collect::<Result<Vec<_>, _>>()?;
+                post = Some(kinds);
+            }
+            "--fft" => analysis.fft_size = parse_num(&value("--fft")?, "--fft")?,
+            "--window" => analysis.window = Window::from_name(&value("--window")?)?,
+            "--attack" => analysis.attack = parse_num(&value("--attack")?, "--attack")?,
+            "--release" => analysis.release = parse_num(&value("--release")?, "--release")?,
+            "--min-db" => analysis.min_db = parse_num(&value("--min-db")?, "--min-db")?,
+            "--max-db" => analysis.max_db = parse_num(&value("--max-db")?, "--max-db")?,
+            "--preset" => preset = Some(PathBuf::from(value("--preset")?)),
+            "--sensitivity" => sensitivity = Some(parse_num(&value("--sensitivity")?, "--sensitivity")?),
+            "--perf" => performance_mode = Some(parse_num(&value("--perf")?, "--perf")?),
+            "--seed" => seed = parse_num(&value("--seed")?, "--seed")?,
+            "-h" | "--help" => return Err(String::new()),
+            s if s.starts_with('-') && s != "-" => return Err(format!("unknown option {}", s)),
+            s if input.is_none() => input = Some(PathBuf::from(s)),
+            s => return Err(format!("unexpected argument {}", s)),
+        }
+    }
+
+    if fps == 0 {
+        return Err("--fps must be positive".into());
+    }
+    if threads == Some(0) {
+        return Err("--threads must be positive".into());
+    }
+    if width == 0 || height == 0 {
+        return Err("--size must be non-zero".into());
+    }
+    if let Some(scale) = scale.filter(|s| !(0.25..=1.0).contains(s)) {
+        return Err(format!("--scale must be in 0.25..=1, got {}", scale));
+    }
+    analysis.validate()?;
+    if palette.is_some() && palette_mode.is_none() {
+        palette_mode = Some(PaletteMode::Time);
+    }
+    Ok(Options {
+        input: input.ok_or("missing input WAV file")?,
+        output: output.ok_or("one of --png or --y4m is required")?,
+        fps,
+        width,
+        height,
+        scale,
+        upscale,
+        scene,
+        lines,
+        caps,
+        faces,
+        depth,
+        palette,
+        palette_mode,
+        threads,
+        post,
+        analysis,
+        preset,
+        sensitivity,
+        performance_mode,
+        seed,
+    })
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str, name: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid value for {}: {}", name, s))
+}
+
+/// Reads a PCM or float WAV and mixes it down to mono f32 in -1..1.
+fn read_wav(path: &Path) -> Result<(Vec<f32>, u32), String> {
+    let mut reader = hound::WavReader::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let spec = reader.spec();
+    let channels = spec.channels.max(1) as usize;
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>().map(|s| s.map(|v| v as f32 * scale)).collect::<Result<_, _>>()
+        }
+    }
+    .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mono = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    Ok((mono, spec.sample_rate))
+}
+
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| format!("{}: {}", path.display(), e))?;
+    writer.write_image_data(rgba).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Writes one RGBA frame as 4:2:0 BT.601 (studio range) planes.
+fn write_y4m_frame(out: &mut dyn Write, width: usize, height: usize, rgba: &[u8], planes: &mut Vec<u8>) -> io::Result<()> {
+    let cw = width.div_ceil(2);
+    let ch = height.div_ceil(2);
+    planes.clear();
+    planes.resize(width * height + cw * ch * 2, 0);
+    let (y_plane, uv) = planes.split_at_mut(width * height);
+    let (u_plane, v_plane) = uv.split_at_mut(cw * ch);
+    for (i, p) in rgba.chunks_exact(4).enumerate() {
+        let (r, g, b) = (p[0] as f32, p[1] as f32, p[2] as f32);
+        y_plane[i] = (16.0 + 0.257 * r + 0.504 * g + 0.098 * b).round() as u8;
+    }
+    for cy in 0..ch {
+        for cx in 0..cw {
+            let (mut r, mut g, mut b, mut n) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+            for y in cy * 2..(cy * 2 + 2).min(height) {
+                for x in cx * 2..(cx * 2 + 2).min(width) {
+                    let i = (y * width + x) * 4;
+                    r += rgba[i] as f32;
+                    g += rgba[i + 1] as f32;
+                    b += rgba[i + 2] as f32;
+                    n += 1.0;
+                }
+            }
+            let (r, g, b) = (r / n, g / n, b / n);
+            u_plane[cy * cw + cx] = (128.0 - 0.148 * r - 0.291 * g + 0.439 * b).round() as u8;
+            v_plane[cy * cw + cx] = (128.0 + 0.439 * r - 0.368 * g - 0.071 * b).round() as u8;
+        }
+    }
+    out.write_all(b"FRAME\n")?;
+    out.write_all(planes)
+}
+
+fn run(opts: Options) -> Result<(), String> {
+    let (samples, sample_rate) = read_wav(&opts.input)?;
+    let frames = (samples.len() as u64 * opts.fps as u64).div_ceil(sample_rate as u64);
+
+    if let Some(count) = opts.threads {
+        tiles::init_threads(count)?;
+    }
+    let mut renderer = Renderer::new(opts.width, opts.height);
+    renderer.set_seed(opts.seed);
+    renderer.set_frame_interval(1.0 / opts.fps as f32);
+    renderer.set_sample_rate(sample_rate as f32);
+    if let Some(path) = &opts.preset {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let preset = Preset::from_json(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        preset.apply(&mut renderer)?;
+    }
+    if let Some(scale) = opts.scale {
+        renderer.set_render_scale(scale);
+    }
+    if let Some(filter) = opts.upscale {
+        renderer.set_upscale_filter(filter);
+    }
+    // Restarted even when unchanged, so the scene's geometry comes from the
+    // same point in the seed's sequence whether or not a preset picked it
+    let scene = opts.scene.clone().unwrap_or_else(|| renderer.scene_name().to_string());
+    renderer.set_scene(&scene)?;
+    if let Some(mode) = opts.lines {
+        renderer.set_line_mode(mode);
+    }
+    if let Some(cap) = opts.caps {
+        renderer.set_line_cap(cap);
+    }
+    if let Some(fill) = opts.faces {
+        renderer.set_face_fill(fill);
+    }
+    if let Some(on) = opts.depth {
+        renderer.set_depth_test(on);
+    }
+    if let Some(palette) = &opts.palette {
+        renderer.set_palette(palette.clone());
+    }
+    if let Some(mode) = opts.palette_mode {
+        renderer.set_palette_mode(mode);
+    }
+    if let Some(mode) = opts.performance_mode {
+        renderer.set_performance_mode(mode);
+    }
+    if let Some(sensitivity) = opts.sensitivity {
+        renderer.set_sensitivity(sensitivity);
+    }
+    // After --perf, so a listed glow or smoothing pass stays on
+    if let Some(kinds) = &opts.post {
+        *renderer.post_chain_mut() = PostChain::from_kinds(kinds);
+    }
+    let mut analyser = Analyser::new(opts.analysis.clone(), sample_rate as f32)?;
+
+    let mut y4m: Option<Box<dyn Write>> = None;
+    match &opts.output {
+        Output::Png(dir) => fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?,
+        Output::Y4m(path) => {
+            let mut out: Box<dyn Write> = if path == "-" {
+                Box::new(BufWriter::new(io::stdout().lock()))
+            } else {
+                Box::new(BufWriter::new(File::create(path).map_err(|e| format!("{}: {}", path, e))?))
+            };
+            writeln!(out, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg", opts.width, opts.height, opts.fps)
+                .map_err(|e| e.to_string())?;
+            y4m = Some(out);
+        }
+    }
+
+    let mut planes = Vec::new();
+    let mut start = 0;
+    for frame in 0..frames {
+        // Everything up to the end of this frame's interval, so the picture doesn't trail the audio
+        let end = (((frame + 1) * sample_rate as u64 / opts.fps as u64) as usize).min(samples.len());
+        analyser.push(&samples[start..end]);
+        start = end;
+        renderer.set_spectrum(analyser.analyse());
+        renderer.render();
+        match (&opts.output, y4m.as_mut()) {
+            (_, Some(out)) => write_y4m_frame(out, opts.width as usize, opts.height as usize, renderer.frame(), &mut planes)
+                .map_err(|e| e.to_string())?,
+            (Output::Png(dir), None) => {
+                let path = dir.join(format!("frame_{:06}.png", frame + 1));
+                write_png(&path, opts.width, opts.height, renderer.frame())?;
+            }
+            (Output::Y4m(_), None) => unreachable!(),
+        }
+    }
+    if let Some(mut out) = y4m {
+        out.flush().map_err(|e| e.to_string())?;
+    }
+    eprintln!("alpine_render: wrote {} frames", frames);
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let opts = match parse_args(&args) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            if !msg.is_empty() {
+                eprintln!("alpine_render: {}", msg);
+            }
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(msg) = run(opts) {
+        eprintln!("alpine_render: {}", msg);
+        process::exit(1);
+    }
+}
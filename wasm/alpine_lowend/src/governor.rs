@@ -0,0 +1,170 @@
+//! Adaptive quality: steps render cost up or down to hold a frame-time budget.
+//!
+//! Feed it the time spent producing each frame (not the display interval) and
+//! apply the level it picks. Moving down is quick; moving back up waits longer
+//! each time a level has proven too slow, so it settles instead of oscillating.
+
+use crate::renderer::Renderer;
+
+/// One rung of the quality ladder.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualityLevel {
+    pub name: &'static str,
+    pub render_scale: f32,
+    /// Low-end ring step and segment count.
+    pub low_end: bool,
+    pub glow: bool,
+    pub smoothing: bool,
+    /// Frames skipped after each rendered one.
+    pub frame_skip: u8,
+}
+
+const fn level(name: &'static str, render_scale: f32, low_end: bool, glow: bool, smoothing: bool, frame_skip: u8) -> QualityLevel {
+    QualityLevel { name, render_scale, low_end, glow, smoothing, frame_skip }
+}
+
+/// Best first; each step is cheaper than the one before.
+pub const QUALITY_LEVELS: &[QualityLevel] = &[
+    level("ultra", 1.0, false, true, true, 0),
+    level("high", 1.0, false, false, true, 0),
+    level("medium", 1.0, false, false, false, 0),
+    level("reduced", 0.75, false, false, false, 0),
+    level("low", 0.75, true, false, false, 0),
+    level("minimal", 0.5, true, false, false, 0),
+    level("skip", 0.5, true, false, false, 1),
+];
+
+/// Level matching the renderer's default settings (performance mode 2).
+pub const DEFAULT_QUALITY_LEVEL: usize = 2;
+
+// Average above budget * DEGRADE_RATIO for DEGRADE_FRAMES frames steps down
+const DEGRADE_RATIO: f32 = 1.1;
+const DEGRADE_FRAMES: u32 = 20;
+// Average below budget * UPGRADE_RATIO for the level's wait steps up
+const UPGRADE_RATIO: f32 = 0.7;
+const UPGRADE_FRAMES: u32 = 120;
+const MAX_UPGRADE_FRAMES: u32 = 120 * 32;
+// Weight of the newest sample in the running average
+const AVERAGE_WEIGHT: f32 = 0.1;
+
+pub struct Governor {
+    target_ms: f32,
+    level: usize,
+    avg_ms: Option<f32>,
+    over: u32,
+    under: u32,
+    // Frames to wait before retrying each level; doubles whenever it was too slow
+    upgrade_wait: Vec<u32>,
+}
+
+impl Governor {
+    pub fn new(target_ms: f32) -> Result<Governor, String> {
+        check_target(target_ms)?;
+        Ok(Governor {
+            target_ms,
+            level: DEFAULT_QUALITY_LEVEL,
+            avg_ms: None,
+            over: 0,
+            under: 0,
+            upgrade_wait: vec![UPGRADE_FRAMES; QUALITY_LEVELS.len()],
+        })
+    }
+
+    pub fn target_ms(&self) -> f32 {
+        self.target_ms
+    }
+
+    pub fn set_target_ms(&mut self, target_ms: f32) -> Result<(), String> {
+        check_target(target_ms)?;
+        self.target_ms = target_ms;
+        self.upgrade_wait.fill(UPGRADE_FRAMES);
+        self.over = 0;
+        self.under = 0;
+        Ok(())
+    }
+
+    /// Index into `QUALITY_LEVELS`; 0 is best.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn quality(&self) -> &'static QualityLevel {
+        &QUALITY_LEVELS[self.level]
+    }
+
+    /// Running average of the recorded frame times.
+    pub fn average_ms(&self) -> Option<f32> {
+        self.avg_ms
+    }
+
+    /// Records one frame's cost; returns the new level when it changes.
+    pub fn record(&mut self, frame_ms: f32) -> Option<usize> {
+        if !frame_ms.is_finite() || frame_ms < 0.0 {
+            return None;
+        }
+        let avg = match self.avg_ms {
+            Some(avg) => avg + (frame_ms - avg) * AVERAGE_WEIGHT,
+            None => frame_ms,
+        };
+        self.avg_ms = Some(avg);
+
+        if avg > self.target_ms * DEGRADE_RATIO {
+            self.over += 1;
+            self.under = 0;
+        } else if avg < self.target_ms * UPGRADE_RATIO {
+            self.under += 1;
+            self.over = 0;
+        } else {
+            self.over = 0;
+            self.under = 0;
+        }
+
+        if self.over >= DEGRADE_FRAMES && self.level + 1 < QUALITY_LEVELS.len() {
+            let wait = &mut self.upgrade_wait[self.level];
+            *wait = (*wait * 2).min(MAX_UPGRADE_FRAMES);
+            return Some(self.change_level(self.level + 1));
+        }
+        if self.level > 0 && self.under >= self.upgrade_wait[self.level - 1] {
+            return Some(self.change_level(self.level - 1));
+        }
+        None
+    }
+
+    /// Records the cost of the frame `renderer` just produced and applies any
+    /// level change to it. Frames the skip level left out cost next to nothing;
+    /// counting them would pull the average down and make the level swing.
+    pub fn record_render(&mut self, renderer: &mut Renderer, frame_ms: f32) -> Option<usize> {
+        if renderer.frame_skipped() {
+            return None;
+        }
+        let level = self.record(frame_ms)?;
+        self.apply(renderer);
+        Some(level)
+    }
+
+    /// Applies the current level's settings to `renderer`.
+    pub fn apply(&self, renderer: &mut Renderer) {
+        let q = self.quality();
+        renderer.set_render_scale(q.render_scale);
+        renderer.set_low_end_mode(q.low_end);
+        renderer.set_glow(q.glow);
+        renderer.set_smoothing(q.smoothing);
+        renderer.set_frame_skip(q.frame_skip);
+    }
+
+    fn change_level(&mut self, level: usize) -> usize {
+        self.level = level;
+        self.over = 0;
+        self.under = 0;
+        // Start the new level's average fresh so the old cost does not linger
+        self.avg_ms = None;
+        level
+    }
+}
+
+fn check_target(target_ms: f32) -> Result<(), String> {
+    if !target_ms.is_finite() || target_ms <= 0.0 {
+        return Err(format!("target frame time must be positive, got {}", target_ms));
+    }
+    Ok(())
+}
@@ -0,0 +1,204 @@
+//! Gradient palettes for the line colors. A palette is a loop of color stops:
+//! positions run 0..1 and the last stop blends back into the first, so
+//! cycling through it never jumps. The renderer picks positions on it by time,
+//! audio energy or row depth (see `PaletteMode`).
+//!
+//! Palettes are written as a built-in name or as comma-separated `#rrggbb`
+//! stops, optionally placed with `@position`:
+//!
+//! ```text
+//! sunset
+//! #ff0080,#00e0ff,#ffffff
+//! #ff0080@0,#00e0ff@0.2,#101040@0.6
+//! ```
+
+/// Built-in palettes, in the order UIs list them.
+pub const PALETTE_NAMES: &[&str] = &["sunset", "ocean", "neon", "ember", "ice", "mono"];
+
+/// What picks the position on the gradient each frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteMode {
+    /// The classic free-running sine oscillators; the gradient is unused.
+    Sine,
+    /// The whole tunnel shows one color that cycles along the gradient.
+    Time,
+    /// Louder passages move further along the gradient.
+    Energy,
+    /// Each row takes its color from its distance, near to far.
+    Depth,
+}
+
+impl PaletteMode {
+    pub fn from_name(name: &str) -> Result<PaletteMode, String> {
+        match name {
+            "sine" => Ok(PaletteMode::Sine),
+            "time" => Ok(PaletteMode::Time),
+            "energy" => Ok(PaletteMode::Energy),
+            "depth" => Ok(PaletteMode::Depth),
+            _ => Err(format!("unknown palette mode {:?} (expected sine, time, energy or depth)", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaletteMode::Sine => "sine",
+            PaletteMode::Time => "time",
+            PaletteMode::Energy => "energy",
+            PaletteMode::Depth => "depth",
+        }
+    }
+}
+
+/// One color on a gradient.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stop {
+    /// Fraction along the loop, 0..=1.
+    pub position: f32,
+    pub color: [u8; 3],
+}
+
+/// A looping gradient of at least one stop, sorted by position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    stops: Vec<Stop>,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::builtin("sunset").unwrap()
+    }
+}
+
+impl Palette {
+    /// Checks that there is at least one stop and that positions are in
+    /// 0..=1 and never go backwards.
+    pub fn new(stops: Vec<Stop>) -> Result<Palette, String> {
+        if stops.is_empty() {
+            return Err("a palette needs at least one color".into());
+        }
+        if let Some(s) = stops.iter().find(|s| !(0.0..=1.0).contains(&s.position)) {
+            return Err(format!("palette stop positions must be in 0..=1, got {}", s.position));
+        }
+        if stops.windows(2).any(|w| w[1].position < w[0].position) {
+            return Err("palette stop positions must not decrease".into());
+        }
+        Ok(Palette { stops })
+    }
+
+    pub fn builtin(name: &str) -> Result<Palette, String> {
+        let spec = match name {
+            "sunset" => "#ff2d6f,#ff8c1a,#ffd23f,#7b2ff7",
+            "ocean" => "#0a3d91,#00a6d6,#3df5c4,#0a6b8a",
+            "neon" => "#ff00c8,#00f0ff,#7dff00,#8a00ff",
+            "ember" => "#ff3300,#ffa200,#8c1000",
+            "ice" => "#ffffff,#8fd8ff,#3a6cff",
+            "mono" => "#ffffff",
+            _ => return Err(format!("unknown palette {:?} (expected {} or #rrggbb stops)", name, PALETTE_NAMES.join(", "))),
+        };
+        Palette::parse(spec)
+    }
+
+    /// Parses a built-in name or a stop list (see the module docs). Stops
+    /// without `@position` are spread evenly; mixing both forms is an error.
+    pub fn parse(spec: &str) -> Result<Palette, String> {
+        let spec = spec.trim();
+        if !spec.starts_with('#') {
+            return Palette::builtin(spec);
+        }
+        let items: Vec<&str> = spec.split(',').map(str::trim).collect();
+        let placed = items.iter().filter(|s| s.contains('@')).count();
+        if placed != 0 && placed != items.len() {
+            return Err("either every palette stop has an @position or none does".into());
+        }
+        let mut stops = Vec::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            let (hex, at) = match item.split_once('@') {
+                Some((hex, at)) => (hex, Some(at)),
+                None => (*item, None),
+            };
+            let position = match at {
+                Some(at) => at.parse().map_err(|_| format!("invalid palette stop position {:?}", at))?,
+                None => i as f32 / items.len() as f32,
+            };
+            stops.push(Stop { position, color: parse_hex(hex)? });
+        }
+        Palette::new(stops)
+    }
+
+    pub fn stops(&self) -> &[Stop] {
+        &self.stops
+    }
+
+    /// The palette as a stop list with positions, which `parse` reads back unchanged.
+    pub fn to_spec(&self) -> String {
+        let stops: Vec<String> = self
+            .stops
+            .iter()
+            .map(|s| format!("#{:02x}{:02x}{:02x}@{}", s.color[0], s.color[1], s.color[2], s.position))
+            .collect();
+        stops.join(",")
+    }
+
+    /// Color at `t` along the loop (wrapped into 0..1), each channel 0..1.
+    pub fn sample(&self, t: f32) -> [f32; 3] {
+        let t = if t.is_finite() { t.rem_euclid(1.0) } else { 0.0 };
+        let next = self.stops.iter().position(|s| s.position > t);
+        // Between the last stop and the first, the gradient wraps around
+        let (a, b, start, end) = match next {
+            Some(0) => {
+                let last = self.stops.last().unwrap();
+                (last, &self.stops[0], last.position - 1.0, self.stops[0].position)
+            }
+            Some(i) => (&self.stops[i - 1], &self.stops[i], self.stops[i - 1].position, self.stops[i].position),
+            None => {
+                let last = self.stops.last().unwrap();
+                (last, &self.stops[0], last.position, self.stops[0].position + 1.0)
+            }
+        };
+        let f = if end > start { (t - start) / (end - start) } else { 0.0 };
+        let mut out = [0.0; 3];
+        for (c, o) in out.iter_mut().enumerate() {
+            let (ca, cb) = (a.color[c] as f32, b.color[c] as f32);
+            *o = (ca + (cb - ca) * f) / 255.0;
+        }
+        out
+    }
+
+    /// The line color at `t` in the renderer's 0..2 channel range, where a
+    /// full channel matches the sine oscillators' peak, and the slow color
+    /// far rows fade to: the same hue at `floor` of that.
+    pub fn colors(&self, t: f32, floor: f32) -> ((f32, f32, f32), (f32, f32, f32)) {
+        let [r, g, b] = self.sample(t);
+        ((r * 2.0, g * 2.0, b * 2.0), (r * floor, g * floor, b * floor))
+    }
+}
+
+/// A palette positioned for one frame in depth mode: scenes look up each
+/// row's colors by its depth instead of using `FrameInput::color1`/`color2`.
+#[derive(Clone, Copy, Debug)]
+pub struct DepthGradient<'a> {
+    pub palette: &'a Palette,
+    /// Position of the nearest row.
+    pub offset: f32,
+    /// How far along the loop the farthest row is from the nearest.
+    pub spread: f32,
+    /// `color_floor`, the brightness of the slow color.
+    pub floor: f32,
+}
+
+impl DepthGradient<'_> {
+    /// Line and slow color for a row at `depth`, 0 nearest to 1 farthest.
+    pub fn colors(&self, depth: f32) -> ((f32, f32, f32), (f32, f32, f32)) {
+        self.palette.colors(self.offset + depth * self.spread, self.floor)
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<[u8; 3], String> {
+    let digits = hex.strip_prefix('#').filter(|d| d.len() == 6 && d.is_ascii());
+    let digits = digits.ok_or_else(|| format!("palette colors must be #rrggbb, got {:?}", hex))?;
+    let mut color = [0u8; 3];
+    for (c, out) in color.iter_mut().enumerate() {
+        *out = u8::from_str_radix(&digits[c * 2..c * 2 + 2], 16).map_err(|_| format!("palette colors must be #rrggbb, got {:?}", hex))?;
+    }
+    Ok(color)
+}
@@ -0,0 +1,258 @@
+//! Runtime-tunable tunnel parameters, addressable by name with validated ranges.
+
+/// Range and behavior of one named parameter.
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    /// Only whole numbers are accepted.
+    pub integer: bool,
+    /// Changing it rebuilds the tunnel rows.
+    pub geometry: bool,
+}
+
+pub(crate) const fn spec(name: &'static str, min: f32, max: f32, integer: bool, geometry: bool) -> ParamSpec {
+    ParamSpec { name, min, max, integer, geometry }
+}
+
+pub const PARAM_SPECS: &[ParamSpec] = &[
+    spec("fov", 50.0, 600.0, false, true),
+    spec("speed", 0.0, 10.0, false, false),
+    spec("perspective_offset_x", -2000.0, 2000.0, false, false),
+    spec("perspective_offset_y", -2000.0, 2000.0, false, false),
+    spec("perspective_depth_divisor", 10.0, 5000.0, false, false),
+    spec("mouse_steer", 0.0, 1.0, false, false),
+    spec("steer_inertia", 0.0, 0.99, false, false),
+    spec("saturation_boost", 0.0, 10.0, false, false),
+    spec("color_speed", 0.0, 10.0, false, false),
+    spec("color_floor", 0.0, 2.0, false, false),
+    spec("palette_spread", 0.0, 4.0, false, false),
+    spec("fog_near", 0.0, 1.0, false, false),
+    spec("fog_far", 0.0, 1.0, false, false),
+    spec("fog_brightness", 0.0, 1.0, false, false),
+    spec("glow_base", 0.0, 1.0, false, false),
+    spec("outer_glow_radius", 1.0, 32.0, true, false),
+    spec("outer_glow_opacity", 0.0, 1.0, false, false),
+    spec("glow_gamma", 0.1, 4.0, false, false),
+    spec("beam_add", 0.0, 4.0, false, false),
+    spec("smoothing", 0.0, 0.95, false, false),
+    spec("tempo_lock", 0.0, 1.0, false, false),
+    spec("kick_speed", 0.0, 10.0, false, false),
+    spec("hat_sparkle", 0.0, 1.0, false, false),
+    spec("mesh_thickness", 1.0, 16.0, false, false),
+    spec("thickness_audio", 0.0, 8.0, false, false),
+    spec("face_alpha", 0.0, 1.0, false, false),
+    spec("face_brightness", 0.0, 2.0, false, false),
+    spec("ring_radius", 10.0, 400.0, false, true),
+    spec("ring_step", 1.0, 50.0, true, true),
+    spec("low_end_ring_step", 1.0, 50.0, true, true),
+    spec("segments", 8.0, 256.0, true, true),
+    spec("low_end_segments", 8.0, 256.0, true, true),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TunnelParams {
+    pub fov: f32,
+    pub speed: f32,
+    pub perspective_offset_x: f32,
+    pub perspective_offset_y: f32,
+    pub perspective_depth_divisor: f32,
+    pub mouse_steer: f32,        // how far the perspective center moves toward the pointer, 0..1
+    pub steer_inertia: f32,      // per-frame fraction of the old steering offset kept
+    pub saturation_boost: f32,   // >1 increases color saturation
+    pub color_speed: f32,        // scales how fast the color oscillators cycle
+    pub color_floor: f32,        // minimum level of the slow color oscillator (0..2)
+    pub palette_spread: f32,     // gradient loops covered by full energy or near-to-far depth
+    pub fog_near: f32,           // fraction of fov where fog starts (far half of tunnel)
+    pub fog_far: f32,            // fraction of fov where fog is full
+    pub fog_brightness: f32,     // 0..1, 0 = black fog
+    pub glow_base: f32,
+    pub outer_glow_radius: i32,  // balanced blur radius
+    pub outer_glow_opacity: f32, // overall opacity of glow
+    pub glow_gamma: f32,         // <1 brightens halo falloff
+    pub beam_add: f32,           // additive beam strength
+    pub smoothing: f32,          // temporal smoothing weight of the previous frame
+    pub tempo_lock: f32,         // 0 = pulse on bass bumps, 1 = pulse on the tracked beat
+    pub kick_speed: f32,         // extra tunnel speed on a full-strength kick
+    pub hat_sparkle: f32,        // white flash on the lines per hi-hat hit
+    pub mesh_thickness: f32,     // line width in output pixels, fractions allowed
+    pub thickness_audio: f32,    // extra width when a segment's bin is at full level
+    pub face_alpha: f32,         // opacity of filled faces over what is behind them
+    pub face_brightness: f32,    // filled face color relative to the line color
+    pub ring_radius: f32,
+    pub ring_step: i32,          // z spacing between rows
+    pub low_end_ring_step: i32,
+    pub segments: i32,           // segments per ring
+    pub low_end_segments: i32,
+}
+
+impl Default for TunnelParams {
+    fn default() -> TunnelParams {
+        TunnelParams {
+            fov: 200.0,
+            speed: 0.6,
+            perspective_offset_x: -120.0,
+            perspective_offset_y: -20.0,
+            perspective_depth_divisor: 380.0,
+            mouse_steer: 0.5,
+            steer_inertia: 0.92,
+            saturation_boost: 5.6,
+            color_speed: 1.0,
+            color_floor: 0.25,
+            palette_spread: 1.0,
+            fog_near: 0.20,
+            fog_far: 0.92,
+            fog_brightness: 0.0,
+            glow_base: 0.32,
+            outer_glow_radius: 4,
+            outer_glow_opacity: 0.70,
+            glow_gamma: 0.66,
+            beam_add: 0.85,
+            smoothing: 0.45,
+            tempo_lock: 0.0,
+            kick_speed: 0.0,
+            hat_sparkle: 0.0,
+            mesh_thickness: 1.0,
+            thickness_audio: 0.0,
+            face_alpha: 0.6,
+            face_brightness: 0.5,
+            ring_radius: 75.0,
+            ring_step: 5,
+            low_end_ring_step: 10,
+            segments: 64,
+            low_end_segments: 48,
+        }
+    }
+}
+
+impl TunnelParams {
+    pub fn spec(name: &str) -> Option<&'static ParamSpec> {
+        PARAM_SPECS.iter().find(|s| s.name == name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<f32> {
+        Some(match name {
+            "fov" => self.fov,
+            "speed" => self.speed,
+            "perspective_offset_x" => self.perspective_offset_x,
+            "perspective_offset_y" => self.perspective_offset_y,
+            "perspective_depth_divisor" => self.perspective_depth_divisor,
+            "mouse_steer" => self.mouse_steer,
+            "steer_inertia" => self.steer_inertia,
+            "saturation_boost" => self.saturation_boost,
+            "color_speed" => self.color_speed,
+            "color_floor" => self.color_floor,
+            "palette_spread" => self.palette_spread,
+            "fog_near" => self.fog_near,
+            "fog_far" => self.fog_far,
+            "fog_brightness" => self.fog_brightness,
+            "glow_base" => self.glow_base,
+            "outer_glow_radius" => self.outer_glow_radius as f32,
+            "outer_glow_opacity" => self.outer_glow_opacity,
+            "glow_gamma" => self.glow_gamma,
+            "beam_add" => self.beam_add,
+            "smoothing" => self.smoothing,
+            "tempo_lock" => self.tempo_lock,
+            "kick_speed" => self.kick_speed,
+            "hat_sparkle" => self.hat_sparkle,
+            "mesh_thickness" => self.mesh_thickness,
+            "thickness_audio" => self.thickness_audio,
+            "face_alpha" => self.face_alpha,
+            "face_brightness" => self.face_brightness,
+            "ring_radius" => self.ring_radius,
+            "ring_step" => self.ring_step as f32,
+            "low_end_ring_step" => self.low_end_ring_step as f32,
+            "segments" => self.segments as f32,
+            "low_end_segments" => self.low_end_segments as f32,
+            _ => return None,
+        })
+    }
+
+    /// Sets one parameter after checking its range; leaves `self` untouched on error.
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), String> {
+        self.set_many(&[(name, value)])
+    }
+
+    /// Sets several parameters at once. Cross-parameter checks run after all
+    /// values are in, so e.g. both fog bounds can move past each other.
+    pub fn set_many(&mut self, values: &[(&str, f32)]) -> Result<(), String> {
+        let mut next = *self;
+        for &(name, value) in values {
+            next.assign(name, value)?;
+        }
+        next.check_fog()?;
+        *self = next;
+        Ok(())
+    }
+
+    fn assign(&mut self, name: &str, value: f32) -> Result<(), String> {
+        let spec = TunnelParams::spec(name).ok_or_else(|| format!("unknown parameter {:?}", name))?;
+        check(spec, value)?;
+        match name {
+            "fov" => self.fov = value,
+            "speed" => self.speed = value,
+            "perspective_offset_x" => self.perspective_offset_x = value,
+            "perspective_offset_y" => self.perspective_offset_y = value,
+            "perspective_depth_divisor" => self.perspective_depth_divisor = value,
+            "mouse_steer" => self.mouse_steer = value,
+            "steer_inertia" => self.steer_inertia = value,
+            "saturation_boost" => self.saturation_boost = value,
+            "color_speed" => self.color_speed = value,
+            "color_floor" => self.color_floor = value,
+            "palette_spread" => self.palette_spread = value,
+            "fog_near" => self.fog_near = value,
+            "fog_far" => self.fog_far = value,
+            "fog_brightness" => self.fog_brightness = value,
+            "glow_base" => self.glow_base = value,
+            "outer_glow_radius" => self.outer_glow_radius = value as i32,
+            "outer_glow_opacity" => self.outer_glow_opacity = value,
+            "glow_gamma" => self.glow_gamma = value,
+            "beam_add" => self.beam_add = value,
+            "smoothing" => self.smoothing = value,
+            "tempo_lock" => self.tempo_lock = value,
+            "kick_speed" => self.kick_speed = value,
+            "hat_sparkle" => self.hat_sparkle = value,
+            "mesh_thickness" => self.mesh_thickness = value,
+            "thickness_audio" => self.thickness_audio = value,
+            "face_alpha" => self.face_alpha = value,
+            "face_brightness" => self.face_brightness = value,
+            "ring_radius" => self.ring_radius = value,
+            "ring_step" => self.ring_step = value as i32,
+            "low_end_ring_step" => self.low_end_ring_step = value as i32,
+            "segments" => self.segments = value as i32,
+            "low_end_segments" => self.low_end_segments = value as i32,
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Checks every parameter against its range.
+    pub fn validate(&self) -> Result<(), String> {
+        for spec in PARAM_SPECS {
+            check(spec, self.get(spec.name).unwrap())?;
+        }
+        self.check_fog()
+    }
+
+    /// True when switching from `self` to `other` needs the rows rebuilt.
+    pub fn geometry_differs(&self, other: &TunnelParams) -> bool {
+        PARAM_SPECS.iter().filter(|s| s.geometry).any(|s| self.get(s.name) != other.get(s.name))
+    }
+
+    fn check_fog(&self) -> Result<(), String> {
+        if self.fog_far <= self.fog_near {
+            return Err(format!("fog_far ({}) must be greater than fog_near ({})", self.fog_far, self.fog_near));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn check(spec: &ParamSpec, value: f32) -> Result<(), String> {
+    if !value.is_finite() || value < spec.min || value > spec.max {
+        return Err(format!("{} must be in {}..={}, got {}", spec.name, spec.min, spec.max, value));
+    }
+    if spec.integer && value.fract() != 0.0 {
+        return Err(format!("{} must be a whole number, got {}", spec.name, value));
+    }
+    Ok(())
+}
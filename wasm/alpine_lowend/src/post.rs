@@ -0,0 +1,449 @@
+//! Post-processing: an ordered chain of passes run on every frame after the
+//! scene draws. Passes can be added, removed, reordered, toggled and tuned at
+//! runtime; the frame is upscaled to output size just before the first pass
+//! that runs at output resolution, so everything after it stays crisp.
+
+use crate::params::{check, spec, ParamSpec, TunnelParams};
+use crate::pixel;
+use crate::tiles;
+
+/// Built-in post-processing passes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostKind {
+    /// Blurred halo blended over the lines; sized and weighted by the
+    /// `glow_*`, `outer_glow_*` and `beam_add` tunnel params. The blur runs on
+    /// a downsampled copy with running sums, so its cost does not grow with the radius.
+    Glow,
+    /// Blends in this pass's previous output (the `smoothing` tunnel param).
+    Smoothing,
+    /// Dims the frame during quiet passages.
+    Darken,
+    /// Darkens every few rows, at output resolution.
+    Scanlines,
+    /// Soft color inversion while the pointer is held down, at output resolution.
+    Invert,
+}
+
+// Own parameters of each pass kind, with their defaults
+const GLOW_PARAMS: &[(ParamSpec, f32)] = &[
+    (spec("line_boost", 1.0, 2.0, false, false), 1.10), // brightness of the lines under the halo
+    (spec("downsample", 1.0, 4.0, true, false), 2.0),   // blur at 1/n of the frame size
+    (spec("iterations", 1.0, 4.0, true, false), 2.0),   // box passes; more look closer to a Gaussian
+];
+const DARKEN_PARAMS: &[(ParamSpec, f32)] = &[
+    (spec("floor", 0.0, 1.0, false, false), 0.85), // brightness at silence
+    (spec("gain", 0.0, 4.0, false, false), 1.15),  // energy scale before reaching full brightness
+];
+const SCANLINE_PARAMS: &[(ParamSpec, f32)] = &[
+    (spec("period", 2.0, 16.0, true, false), 3.0), // rows per darkened row
+    (spec("level", 0.0, 1.0, false, false), 0.88), // brightness of the darkened rows
+];
+
+impl PostKind {
+    pub const ALL: [PostKind; 5] = [PostKind::Glow, PostKind::Smoothing, PostKind::Darken, PostKind::Scanlines, PostKind::Invert];
+
+    pub fn from_name(name: &str) -> Result<PostKind, String> {
+        PostKind::ALL.into_iter().find(|k| k.name() == name).ok_or_else(|| {
+            format!("unknown post pass {:?} (expected glow, smoothing, darken, scanlines or invert)", name)
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PostKind::Glow => "glow",
+            PostKind::Smoothing => "smoothing",
+            PostKind::Darken => "darken",
+            PostKind::Scanlines => "scanlines",
+            PostKind::Invert => "invert",
+        }
+    }
+
+    /// Runs on the output-sized frame rather than at the internal render scale.
+    pub fn at_output(self) -> bool {
+        matches!(self, PostKind::Scanlines | PostKind::Invert)
+    }
+
+    /// Skipped in low-end mode.
+    pub fn expensive(self) -> bool {
+        matches!(self, PostKind::Glow | PostKind::Smoothing)
+    }
+
+    fn params(self) -> &'static [(ParamSpec, f32)] {
+        match self {
+            PostKind::Glow => GLOW_PARAMS,
+            PostKind::Darken => DARKEN_PARAMS,
+            PostKind::Scanlines => SCANLINE_PARAMS,
+            PostKind::Smoothing | PostKind::Invert => &[],
+        }
+    }
+}
+
+/// Frame-wide values the passes read.
+pub(crate) struct PostInput<'a> {
+    pub energy: f32,
+    pub bass: f32,
+    pub steps: f32,
+    /// Pixels of the frame being processed per output pixel.
+    pub scale: f32,
+    /// Current strength of the pointer-held inversion, 0..255.
+    pub invert: u8,
+    pub params: &'a TunnelParams,
+    /// Requested rows per band, 0 for automatic (see `tiles::band_rows`).
+    pub tile_rows: usize,
+}
+
+/// Scratch buffers shared by every pass: the glow's downsampled copy and its
+/// blur temporary. Sized by `PostChain::glow_scratch_len` when the frame size
+/// changes, and grown by a glow pass that needs more.
+#[derive(Default)]
+pub(crate) struct PostScratch {
+    pub temp: Vec<u8>,
+    pub glow: Vec<u8>,
+}
+
+impl PostScratch {
+    /// Sets both buffers to `len` bytes. Capacity only grows, so glow passes
+    /// at different `downsample` factors share them without reallocating.
+    pub(crate) fn fit(&mut self, len: usize) {
+        for buf in [&mut self.temp, &mut self.glow] {
+            buf.resize(len, 0);
+        }
+    }
+
+    /// Sizes both buffers for a new frame size and releases what they no longer need.
+    pub(crate) fn refit(&mut self, len: usize) {
+        for buf in [&mut self.temp, &mut self.glow] {
+            buf.resize(len, 0);
+            buf.shrink_to(len);
+        }
+    }
+}
+
+/// One entry in the chain: a pass kind, its enable flag and its own parameters.
+#[derive(Clone, Debug)]
+pub struct PostPass {
+    kind: PostKind,
+    enabled: bool,
+    values: Vec<f32>,
+    // Last output, for smoothing; reset whenever the frame size changes
+    history: Vec<u8>,
+    // Glow gamma curve as a table, and the glow_gamma it was built for
+    gamma_lut: Vec<u8>,
+    lut_gamma: f32,
+}
+
+impl PostPass {
+    pub fn new(kind: PostKind) -> PostPass {
+        PostPass {
+            kind,
+            enabled: true,
+            values: kind.params().iter().map(|(_, v)| *v).collect(),
+            history: Vec::new(),
+            gamma_lut: Vec::new(),
+            lut_gamma: 0.0,
+        }
+    }
+
+    pub fn kind(&self) -> PostKind {
+        self.kind
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn param_names(&self) -> Vec<&'static str> {
+        self.kind.params().iter().map(|(s, _)| s.name).collect()
+    }
+
+    pub fn param(&self, name: &str) -> Option<f32> {
+        let i = self.kind.params().iter().position(|(s, _)| s.name == name)?;
+        Some(self.values[i])
+    }
+
+    /// Sets one of this pass's parameters after checking its range.
+    pub fn set_param(&mut self, name: &str, value: f32) -> Result<(), String> {
+        let i = self
+            .kind
+            .params()
+            .iter()
+            .position(|(s, _)| s.name == name)
+            .ok_or_else(|| format!("{} has no parameter {:?}", self.kind.name(), name))?;
+        check(&self.kind.params()[i].0, value)?;
+        self.values[i] = value;
+        Ok(())
+    }
+
+    pub(crate) fn apply(&mut self, frame: &mut [u8], w: usize, h: usize, input: &PostInput, scratch: &mut PostScratch) {
+        let p = input.params;
+        let (stride, rows) = (w * 4, tiles::band_rows(h, input.tile_rows));
+        match self.kind {
+            PostKind::Glow => self.glow(frame, w, h, input, scratch),
+            PostKind::Smoothing => {
+                if self.history.len() != frame.len() {
+                    self.history.clear();
+                    self.history.resize(frame.len(), 0);
+                }
+                let s = p.smoothing.powf(input.steps);
+                let bands: Vec<_> = frame.chunks_mut(stride * rows).zip(self.history.chunks_mut(stride * rows)).collect();
+                tiles::for_each(bands, |(band, prev)| pixel::smooth(band, prev, s));
+            }
+            PostKind::Darken => {
+                let (floor, gain) = (self.values[0], self.values[1]);
+                let quiet = (input.energy * gain).min(1.0);
+                let darken_factor = floor + (1.0 - floor) * quiet;
+                if darken_factor < 0.999 {
+                    tiles::bands(frame, stride, rows, |_, band| pixel::scale_rgb(band, darken_factor));
+                }
+            }
+            PostKind::Scanlines => {
+                let (period, level) = (self.values[0] as usize, self.values[1]);
+                tiles::bands(frame, stride, rows, |top, band| {
+                    for (y, row) in (top..).zip(band.chunks_exact_mut(stride)) {
+                        if y % period == 0 {
+                            pixel::scale_rgb(row, level);
+                        }
+                    }
+                });
+            }
+            PostKind::Invert => {
+                if input.invert > 0 {
+                    tiles::bands(frame, stride, rows, |_, band| pixel::soft_invert(band, input.invert));
+                }
+            }
+        }
+    }
+
+    // Bloom: average the frame down, box-blur that copy with running sums, shape
+    // it through the gamma table and add it back with bilinear upsampling
+    fn glow(&mut self, frame: &mut [u8], w: usize, h: usize, input: &PostInput, scratch: &mut PostScratch) {
+        let p = input.params;
+        let (line_boost, factor, iterations) = (self.values[0], self.values[1] as usize, self.values[2] as usize);
+        if self.gamma_lut.is_empty() || self.lut_gamma != p.glow_gamma {
+            self.gamma_lut = (0..256).map(|v| ((v as f32 / 255.0).powf(p.glow_gamma) * 255.0).min(255.0) as u8).collect();
+            self.lut_gamma = p.glow_gamma;
+        }
+        let (sw, sh) = (w.div_ceil(factor), h.div_ceil(factor));
+        scratch.fit(sw * sh * 4);
+        let small = &mut scratch.glow;
+        let temp = &mut scratch.temp;
+        let (small_stride, small_rows) = (sw * 4, tiles::band_rows(sh, input.tile_rows));
+        let frame_src = &*frame;
+        tiles::bands(small, small_stride, small_rows, |top, band| downsample(frame_src, w, h, band, factor, top));
+
+        // Stacked boxes widen the kernel by about sqrt(iterations); shrink each to keep the halo size
+        let radius = p.outer_glow_radius as f32 * input.scale / factor as f32 / (iterations as f32).sqrt();
+        let rad = (radius.round() as usize).max(1);
+        let lut = &self.gamma_lut;
+        for i in 0..iterations {
+            let bands: Vec<_> = small.chunks(small_stride * small_rows).zip(temp.chunks_mut(small_stride * small_rows)).collect();
+            tiles::for_each(bands, |(src, dst)| blur_rows(src, dst, sw, rad));
+            let (src, last) = (&*temp, i + 1 == iterations);
+            tiles::bands(small, small_stride, small_rows, |top, band| {
+                blur_columns(src, band, sw, sh, top, rad);
+                if last {
+                    for c in band.iter_mut() {
+                        *c = lut[*c as usize];
+                    }
+                }
+            });
+        }
+
+        // out = base * line_boost + glow * opacity * beam_add, in 8.8 fixed point
+        let glow_strength = (p.glow_base + input.energy * 0.55 + input.bass * 0.45).clamp(0.22, 1.05);
+        let opacity = (p.outer_glow_opacity * glow_strength).min(1.0);
+        let boost = (line_boost * 256.0).round() as u16;
+        let gain = (opacity * p.beam_add * 256.0).round() as u16;
+        let cols: Vec<(usize, usize, u32)> = (0..w).map(|x| upsample_tap(x, factor, sw)).collect();
+        let small = &*small;
+        tiles::bands(frame, w * 4, tiles::band_rows(h, input.tile_rows), |first, band| {
+            let mut halo = vec![0u8; w * 4];
+            for (y, out) in (first..).zip(band.chunks_exact_mut(w * 4)) {
+                let (y0, y1, fy) = upsample_tap(y, factor, sh);
+                let (row0, row1) = (&small[y0 * sw * 4..], &small[y1 * sw * 4..]);
+                for (px, &(x0, x1, fx)) in halo.chunks_exact_mut(4).zip(&cols) {
+                    for c in 0..3 {
+                        let top = row0[x0 * 4 + c] as u32 * (256 - fx) + row0[x1 * 4 + c] as u32 * fx;
+                        let bottom = row1[x0 * 4 + c] as u32 * (256 - fx) + row1[x1 * 4 + c] as u32 * fx;
+                        px[c] = ((top * (256 - fy) + bottom * fy) >> 16) as u8;
+                    }
+                }
+                pixel::add_glow(out, &halo, boost, gain);
+            }
+        });
+    }
+
+    // Drops smoothing history, e.g. after the internal size changed
+    fn reset(&mut self) {
+        self.history.clear();
+    }
+}
+
+// Averages each `factor` x `factor` block of `src` into one pixel of `dst`,
+// which holds the downsampled rows from `top` on
+fn downsample(src: &[u8], w: usize, h: usize, dst: &mut [u8], factor: usize, top: usize) {
+    let sw = w.div_ceil(factor);
+    for (i, px) in dst.chunks_exact_mut(4).enumerate() {
+        let (x0, y0) = (i % sw * factor, (top + i / sw) * factor);
+        let (x1, y1) = ((x0 + factor).min(w), (y0 + factor).min(h));
+        let mut sum = [0u32; 3];
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let o = (y * w + x) * 4;
+                sum[0] += src[o] as u32;
+                sum[1] += src[o + 1] as u32;
+                sum[2] += src[o + 2] as u32;
+            }
+        }
+        let n = ((x1 - x0) * (y1 - y0)) as u32;
+        px[0] = (sum[0] / n) as u8;
+        px[1] = (sum[1] / n) as u8;
+        px[2] = (sum[2] / n) as u8;
+        px[3] = 255;
+    }
+}
+
+// Box filter of radius `rad` along each row of `src`, keeping a running sum so
+// each pixel costs the same at any radius; edges clamp
+fn blur_rows(src: &[u8], dst: &mut [u8], w: usize, rad: usize) {
+    let win = (rad * 2 + 1) as u32;
+    for (row, out) in src.chunks_exact(w * 4).zip(dst.chunks_exact_mut(w * 4)) {
+        let at = |x: usize| x.min(w - 1) * 4;
+        let mut sum = [0u32; 3];
+        // Window for pixel 0: rad copies of the edge, then pixels 0..=rad
+        for c in 0..3 {
+            sum[c] = row[c] as u32 * rad as u32;
+        }
+        for x in 0..=rad {
+            for c in 0..3 {
+                sum[c] += row[at(x) + c] as u32;
+            }
+        }
+        for x in 0..w {
+            for c in 0..3 {
+                out[x * 4 + c] = (sum[c] / win) as u8;
+            }
+            let (old, new) = (at(x.saturating_sub(rad)), at(x + rad + 1));
+            for c in 0..3 {
+                sum[c] = sum[c] + row[new + c] as u32 - row[old + c] as u32;
+            }
+        }
+    }
+}
+
+// The same filter down the columns of the `w` x `h` image `src`, writing rows
+// `top..` into `dst`; the running sums cover a whole row at a time
+fn blur_columns(src: &[u8], dst: &mut [u8], w: usize, h: usize, top: usize, rad: usize) {
+    let win = (rad * 2 + 1) as u32;
+    let row = |y: isize| &src[y.clamp(0, h as isize - 1) as usize * w * 4..][..w * 4];
+    let mut sums = vec![0u32; w * 4];
+    for y in top as isize - rad as isize..=(top + rad) as isize {
+        for (sum, &v) in sums.iter_mut().zip(row(y)) {
+            *sum += v as u32;
+        }
+    }
+    for (y, out) in (top as isize..).zip(dst.chunks_exact_mut(w * 4)) {
+        for (o, sum) in out.iter_mut().zip(&sums) {
+            *o = (sum / win) as u8;
+        }
+        for ((sum, &old), &new) in sums.iter_mut().zip(row(y - rad as isize)).zip(row(y + rad as isize + 1)) {
+            *sum = *sum + new as u32 - old as u32;
+        }
+    }
+}
+
+// Low-res neighbours of output coordinate `x` and the weight (0..256) of the second
+fn upsample_tap(x: usize, factor: usize, len: usize) -> (usize, usize, u32) {
+    let pos = ((x as f32 + 0.5) / factor as f32 - 0.5).max(0.0);
+    let x0 = (pos as usize).min(len - 1);
+    let x1 = (x0 + 1).min(len - 1);
+    (x0, x1, ((pos - x0 as f32) * 256.0) as u32)
+}
+
+/// The ordered list of passes. The default chain is glow and smoothing (both
+/// off until enabled), darken, scanlines and invert, which is the classic look.
+#[derive(Clone, Debug)]
+pub struct PostChain {
+    passes: Vec<PostPass>,
+}
+
+impl Default for PostChain {
+    fn default() -> PostChain {
+        let mut passes: Vec<PostPass> = PostKind::ALL.into_iter().map(PostPass::new).collect();
+        passes[0].enabled = false;
+        passes[1].enabled = false;
+        PostChain { passes }
+    }
+}
+
+impl PostChain {
+    /// A chain of the given passes, all enabled.
+    pub fn from_kinds(kinds: &[PostKind]) -> PostChain {
+        PostChain { passes: kinds.iter().copied().map(PostPass::new).collect() }
+    }
+
+    pub fn passes(&self) -> &[PostPass] {
+        &self.passes
+    }
+
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Result<&mut PostPass, String> {
+        let len = self.passes.len();
+        self.passes.get_mut(index).ok_or_else(|| format!("no post pass at {} (chain has {})", index, len))
+    }
+
+    /// Inserts a new enabled pass at `index`, or at the end if it is past it.
+    pub fn insert(&mut self, index: usize, kind: PostKind) {
+        self.passes.insert(index.min(self.passes.len()), PostPass::new(kind));
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<PostPass, String> {
+        self.get_mut(index)?;
+        Ok(self.passes.remove(index))
+    }
+
+    /// Moves the pass at `from` so it ends up at `to`.
+    pub fn move_pass(&mut self, from: usize, to: usize) -> Result<(), String> {
+        let pass = self.remove(from)?;
+        self.passes.insert(to.min(self.passes.len()), pass);
+        Ok(())
+    }
+
+    /// Enables or disables every pass of `kind`.
+    pub fn set_enabled(&mut self, kind: PostKind, enabled: bool) {
+        for pass in self.passes.iter_mut().filter(|p| p.kind == kind) {
+            pass.enabled = enabled;
+        }
+    }
+
+    /// True if any pass of `kind` is enabled.
+    pub fn enabled(&self, kind: PostKind) -> bool {
+        self.passes.iter().any(|p| p.kind == kind && p.enabled)
+    }
+
+    /// Bytes each glow scratch buffer needs for a `w` x `h` frame: the largest
+    /// downsampled copy an enabled glow pass makes, or 0 without one.
+    pub(crate) fn glow_scratch_len(&self, w: usize, h: usize) -> usize {
+        let glows = self.passes.iter().filter(|p| p.kind == PostKind::Glow && p.enabled);
+        glows.map(|p| w.div_ceil(p.values[1] as usize) * h.div_ceil(p.values[1] as usize) * 4).max().unwrap_or(0)
+    }
+
+    pub(crate) fn passes_mut(&mut self) -> &mut [PostPass] {
+        &mut self.passes
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.passes.iter_mut().for_each(PostPass::reset);
+    }
+}
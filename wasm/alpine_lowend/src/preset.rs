@@ -0,0 +1,518 @@
+//! Named snapshots of every visual setting, recalled from the PRESET 1-4 slots.
+//!
+//! Presets are exchanged as a versioned JSON document:
+//!
+//! ```json
+//! {
+//!   "format": "alpine-preset",
+//!   "version": 2,
+//!   "name": "ALPINE",
+//!   "sensitivity": 18,
+//!   "performance_mode": 2,
+//!   "glow": false,
+//!   "smoothing": false,
+//!   "render_scale": 0.5,
+//!   "upscale_filter": "nearest",
+//!   "line_mode": "smooth-max",
+//!   "line_cap": "square",
+//!   "face_fill": "depth",
+//!   "depth_test": true,
+//!   "scene": "tunnel",
+//!   "color_phases": [0.1, 0.2, 0.3, 0.4, 0.5, 0.6],
+//!   "palette_mode": "depth",
+//!   "palette": "#ff0080@0,#00e0ff@0.5",
+//!   "post": [
+//!     { "kind": "glow", "enabled": true, "params": { "downsample": 2 } },
+//!     { "kind": "scanlines", "enabled": false, "params": {} }
+//!   ],
+//!   "params": { "fov": 200, "speed": 0.6 }
+//! }
+//! ```
+//!
+//! `format` and `version` are required. Every other key is optional and falls
+//! back to the default preset; `params` may list any subset of the names in
+//! [`PARAM_SPECS`], and omitting `color_phases`, `palette_mode` or `palette`
+//! keeps the current colors. `palette` takes any form `Palette::parse` reads.
+//! `post` replaces the whole post-processing chain, in order; each pass may
+//! omit `enabled` (on) and any of its `params` (their defaults), and its flags
+//! win over `glow` and `smoothing`. Without `post` the chain is kept.
+//! Unknown keys, wrong types and out-of-range values are rejected.
+//!
+//! Version 1 documents still load; they hold only the keys up to `smoothing`
+//! plus `color_phases` and `params`. Version 2 added the render, scene,
+//! palette and `post` keys.
+
+use serde_json::{Map, Value};
+
+use crate::palette::{Palette, PaletteMode};
+use crate::params::{TunnelParams, PARAM_SPECS};
+use crate::post::{PostChain, PostKind, PostPass};
+use crate::renderer::{FaceFill, LineCap, LineMode, Renderer, UpscaleFilter};
+
+pub const PRESET_SLOTS: usize = 4;
+pub const PRESET_FORMAT: &str = "alpine-preset";
+/// Newest document version this build reads and the one it writes.
+pub const PRESET_VERSION: u64 = 2;
+
+const PRESET_KEYS: &[&str] = &[
+    "format",
+    "version",
+    "name",
+    "sensitivity",
+    "performance_mode",
+    "glow",
+    "smoothing",
+    "render_scale",
+    "upscale_filter",
+    "line_mode",
+    "line_cap",
+    "face_fill",
+    "depth_test",
+    "scene",
+    "color_phases",
+    "palette_mode",
+    "palette",
+    "post",
+    "params",
+];
+// Keys a version 1 document may hold
+const PRESET_V1_KEYS: &[&str] =
+    &["format", "version", "name", "sensitivity", "performance_mode", "glow", "smoothing", "color_phases", "params"];
+const POST_PASS_KEYS: &[&str] = &["kind", "enabled", "params"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub params: TunnelParams,
+    pub sensitivity: f32,
+    pub performance_mode: u8,
+    pub glow: bool,
+    pub smoothing: bool,
+    /// Internal resolution and how it is scaled up; `None` keeps the renderer's.
+    pub render_scale: Option<f32>,
+    pub upscale_filter: Option<UpscaleFilter>,
+    /// Line rasterization; `None` keeps the renderer's.
+    pub line_mode: Option<LineMode>,
+    /// End shape of wide lines; `None` keeps the renderer's.
+    pub line_cap: Option<LineCap>,
+    /// Solid faces under the lines; `None` keeps the renderer's.
+    pub face_fill: Option<FaceFill>,
+    /// Per-pixel occlusion; `None` keeps the renderer's.
+    pub depth_test: Option<bool>,
+    /// Visual mode by registry name; `None` keeps the active one.
+    pub scene: Option<String>,
+    /// Color oscillator phases; `None` keeps whatever the renderer is showing.
+    pub color_phases: Option<[f32; 6]>,
+    /// Color source and gradient; `None` keeps the renderer's.
+    pub palette_mode: Option<PaletteMode>,
+    pub palette: Option<Palette>,
+    /// Post-processing chain in order; `None` keeps the renderer's.
+    pub post: Option<Vec<PresetPass>>,
+}
+
+/// One post pass as a preset stores it: its kind, enable flag and every
+/// parameter value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PresetPass {
+    pub kind: PostKind,
+    pub enabled: bool,
+    pub params: Vec<(&'static str, f32)>,
+}
+
+impl PresetPass {
+    pub fn from_pass(pass: &PostPass) -> PresetPass {
+        let params = pass.param_names().into_iter().map(|n| (n, pass.param(n).unwrap())).collect();
+        PresetPass { kind: pass.kind(), enabled: pass.enabled(), params }
+    }
+
+    /// A fresh pass with these settings, after checking every parameter.
+    pub fn to_pass(&self) -> Result<PostPass, String> {
+        let mut pass = PostPass::new(self.kind);
+        pass.set_enabled(self.enabled);
+        for &(name, value) in &self.params {
+            pass.set_param(name, value)?;
+        }
+        Ok(pass)
+    }
+}
+
+impl Preset {
+    /// Captures the renderer's current look, including its color phases.
+    pub fn capture(renderer: &Renderer, name: &str) -> Preset {
+        Preset {
+            name: name.to_string(),
+            params: *renderer.params(),
+            sensitivity: renderer.sensitivity(),
+            performance_mode: renderer.performance_mode(),
+            glow: renderer.glow_enabled(),
+            smoothing: renderer.smoothing_enabled(),
+            render_scale: Some(renderer.render_scale()),
+            upscale_filter: Some(renderer.upscale_filter()),
+            line_mode: Some(renderer.line_mode()),
+            line_cap: Some(renderer.line_cap()),
+            face_fill: Some(renderer.face_fill()),
+            depth_test: Some(renderer.depth_test_enabled()),
+            scene: Some(renderer.scene_name().to_string()),
+            color_phases: Some(renderer.color_phases()),
+            palette_mode: Some(renderer.palette_mode()),
+            palette: Some(renderer.palette().clone()),
+            post: Some(renderer.post_chain().passes().iter().map(PresetPass::from_pass).collect()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.params.validate()?;
+        if !self.sensitivity.is_finite() || self.sensitivity < 1.0 {
+            return Err(format!("sensitivity must be at least 1, got {}", self.sensitivity));
+        }
+        if !(1..=3).contains(&self.performance_mode) {
+            return Err(format!("performance_mode must be 1, 2 or 3, got {}", self.performance_mode));
+        }
+        if let Some(scale) = self.render_scale {
+            if !(0.25..=1.0).contains(&scale) {
+                return Err(format!("render_scale must be in 0.25..=1, got {}", scale));
+            }
+        }
+        if let Some(phases) = self.color_phases {
+            if phases.iter().any(|p| !p.is_finite()) {
+                return Err("color_phases must be finite".into());
+            }
+        }
+        if let Some(post) = &self.post {
+            for pass in post {
+                pass.to_pass()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes to the versioned JSON document described in the module docs.
+    pub fn to_json(&self) -> String {
+        let mut params = Map::new();
+        for spec in PARAM_SPECS {
+            params.insert(spec.name.into(), number(self.params.get(spec.name).unwrap()));
+        }
+        let mut doc = Map::new();
+        doc.insert("format".into(), PRESET_FORMAT.into());
+        doc.insert("version".into(), PRESET_VERSION.into());
+        doc.insert("name".into(), self.name.clone().into());
+        doc.insert("sensitivity".into(), number(self.sensitivity));
+        doc.insert("performance_mode".into(), self.performance_mode.into());
+        doc.insert("glow".into(), self.glow.into());
+        doc.insert("smoothing".into(), self.smoothing.into());
+        if let Some(scale) = self.render_scale {
+            doc.insert("render_scale".into(), number(scale));
+        }
+        if let Some(filter) = self.upscale_filter {
+            doc.insert("upscale_filter".into(), filter.name().into());
+        }
+        if let Some(mode) = self.line_mode {
+            doc.insert("line_mode".into(), mode.name().into());
+        }
+        if let Some(cap) = self.line_cap {
+            doc.insert("line_cap".into(), cap.name().into());
+        }
+        if let Some(fill) = self.face_fill {
+            doc.insert("face_fill".into(), fill.name().into());
+        }
+        if let Some(on) = self.depth_test {
+            doc.insert("depth_test".into(), on.into());
+        }
+        if let Some(name) = &self.scene {
+            doc.insert("scene".into(), name.clone().into());
+        }
+        if let Some(phases) = self.color_phases {
+            doc.insert("color_phases".into(), phases.iter().map(|&p| number(p)).collect());
+        }
+        if let Some(mode) = self.palette_mode {
+            doc.insert("palette_mode".into(), mode.name().into());
+        }
+        if let Some(palette) = &self.palette {
+            doc.insert("palette".into(), palette.to_spec().into());
+        }
+        if let Some(post) = &self.post {
+            let passes = post.iter().map(|pass| {
+                let params: Map<String, Value> = pass.params.iter().map(|&(n, v)| (n.to_string(), number(v))).collect();
+                let mut entry = Map::new();
+                entry.insert("kind".into(), pass.kind.name().into());
+                entry.insert("enabled".into(), pass.enabled.into());
+                entry.insert("params".into(), params.into());
+                Value::Object(entry)
+            });
+            doc.insert("post".into(), passes.collect());
+        }
+        doc.insert("params".into(), params.into());
+        serde_json::to_string_pretty(&Value::Object(doc)).unwrap()
+    }
+
+    /// Parses and validates a preset document.
+    pub fn from_json(text: &str) -> Result<Preset, String> {
+        let doc: Value = serde_json::from_str(text).map_err(|e| format!("invalid preset JSON: {}", e))?;
+        let doc = doc.as_object().ok_or("preset must be a JSON object")?;
+        match doc.get("format").and_then(Value::as_str) {
+            Some(PRESET_FORMAT) => {}
+            _ => return Err(format!("\"format\" must be {:?}", PRESET_FORMAT)),
+        }
+        // Before the keys, so a newer document fails on its version, not on a key it added
+        let version = doc.get("version").and_then(Value::as_u64).ok_or("\"version\" must be a whole number")?;
+        if version == 0 || version > PRESET_VERSION {
+            return Err(format!("unsupported preset version {} (this build reads 1..={})", version, PRESET_VERSION));
+        }
+        if let Some(key) = doc.keys().find(|k| !PRESET_KEYS.contains(&k.as_str())) {
+            return Err(format!("unknown preset key {:?}", key));
+        }
+        if let Some(key) = doc.keys().find(|k| version == 1 && !PRESET_V1_KEYS.contains(&k.as_str())) {
+            return Err(format!("preset key {:?} needs version 2", key));
+        }
+
+        let mut preset = PresetBank::default().slots[0].clone();
+        if let Some(v) = doc.get("name") {
+            preset.name = v.as_str().ok_or("\"name\" must be a string")?.to_string();
+        }
+        if let Some(v) = doc.get("sensitivity") {
+            preset.sensitivity = v.as_f64().ok_or("\"sensitivity\" must be a number")? as f32;
+        }
+        if let Some(v) = doc.get("performance_mode") {
+            let mode = v.as_u64().ok_or("\"performance_mode\" must be 1, 2 or 3")?;
+            preset.performance_mode = u8::try_from(mode).unwrap_or(u8::MAX);
+        }
+        if let Some(v) = doc.get("glow") {
+            preset.glow = v.as_bool().ok_or("\"glow\" must be true or false")?;
+        }
+        if let Some(v) = doc.get("smoothing") {
+            preset.smoothing = v.as_bool().ok_or("\"smoothing\" must be true or false")?;
+        }
+        if let Some(v) = doc.get("render_scale") {
+            preset.render_scale = Some(v.as_f64().ok_or("\"render_scale\" must be a number")? as f32);
+        }
+        if let Some(v) = doc.get("upscale_filter") {
+            preset.upscale_filter = Some(UpscaleFilter::from_name(v.as_str().ok_or("\"upscale_filter\" must be a string")?)?);
+        }
+        if let Some(v) = doc.get("line_mode") {
+            preset.line_mode = Some(LineMode::from_name(v.as_str().ok_or("\"line_mode\" must be a string")?)?);
+        }
+        if let Some(v) = doc.get("line_cap") {
+            preset.line_cap = Some(LineCap::from_name(v.as_str().ok_or("\"line_cap\" must be a string")?)?);
+        }
+        if let Some(v) = doc.get("face_fill") {
+            preset.face_fill = Some(FaceFill::from_name(v.as_str().ok_or("\"face_fill\" must be a string")?)?);
+        }
+        if let Some(v) = doc.get("depth_test") {
+            preset.depth_test = Some(v.as_bool().ok_or("\"depth_test\" must be true or false")?);
+        }
+        if let Some(v) = doc.get("scene") {
+            preset.scene = Some(v.as_str().ok_or("\"scene\" must be a string")?.to_string());
+        }
+        if let Some(v) = doc.get("color_phases") {
+            let list = v.as_array().filter(|a| a.len() == 6).ok_or("\"color_phases\" must be a list of 6 numbers")?;
+            let mut phases = [0.0f32; 6];
+            for (dst, src) in phases.iter_mut().zip(list) {
+                *dst = src.as_f64().ok_or("\"color_phases\" must be a list of 6 numbers")? as f32;
+            }
+            preset.color_phases = Some(phases);
+        }
+        if let Some(v) = doc.get("palette_mode") {
+            preset.palette_mode = Some(PaletteMode::from_name(v.as_str().ok_or("\"palette_mode\" must be a string")?)?);
+        }
+        if let Some(v) = doc.get("palette") {
+            preset.palette = Some(Palette::parse(v.as_str().ok_or("\"palette\" must be a string")?)?);
+        }
+        if let Some(v) = doc.get("post") {
+            let list = v.as_array().ok_or("\"post\" must be a list of passes")?;
+            preset.post = Some(list.iter().map(post_pass_from_json).collect::<Result<_, _>>()?);
+        }
+        if let Some(v) = doc.get("params") {
+            let map = v.as_object().ok_or("\"params\" must be an object")?;
+            let mut values = Vec::with_capacity(map.len());
+            for (name, v) in map {
+                let value = v.as_f64().ok_or_else(|| format!("parameter {:?} must be a number", name))?;
+                values.push((name.as_str(), value as f32));
+            }
+            preset.params.set_many(&values)?;
+        }
+        preset.validate()?;
+        Ok(preset)
+    }
+
+    /// Applies every setting to `renderer`; nothing changes if the preset is invalid.
+    pub fn apply(&self, renderer: &mut Renderer) -> Result<(), String> {
+        self.validate()?;
+        // First, so an unknown scene leaves everything else as it was; the
+        // active scene is not restarted when the preset names it
+        if let Some(name) = self.scene.as_deref().filter(|&n| n != renderer.scene_name()) {
+            renderer.set_scene(name)?;
+        }
+        renderer.set_params(self.params)?;
+        renderer.set_sensitivity(self.sensitivity);
+        renderer.set_performance_mode(self.performance_mode);
+        renderer.set_glow(self.glow);
+        renderer.set_smoothing(self.smoothing);
+        if let Some(scale) = self.render_scale {
+            renderer.set_render_scale(scale);
+        }
+        if let Some(filter) = self.upscale_filter {
+            renderer.set_upscale_filter(filter);
+        }
+        if let Some(mode) = self.line_mode {
+            renderer.set_line_mode(mode);
+        }
+        if let Some(cap) = self.line_cap {
+            renderer.set_line_cap(cap);
+        }
+        if let Some(fill) = self.face_fill {
+            renderer.set_face_fill(fill);
+        }
+        if let Some(on) = self.depth_test {
+            renderer.set_depth_test(on);
+        }
+        if let Some(phases) = self.color_phases {
+            renderer.set_color_phases(phases);
+        }
+        if let Some(mode) = self.palette_mode {
+            renderer.set_palette_mode(mode);
+        }
+        if let Some(palette) = &self.palette {
+            renderer.set_palette(palette.clone());
+        }
+        if let Some(post) = &self.post {
+            let chain = renderer.post_chain_mut();
+            if chain.passes().iter().map(PostPass::kind).eq(post.iter().map(|p| p.kind)) {
+                // Same passes in the same order: retune them in place so
+                // smoothing keeps its history
+                for (i, saved) in post.iter().enumerate() {
+                    let pass = chain.get_mut(i)?;
+                    pass.set_enabled(saved.enabled);
+                    for &(name, value) in &saved.params {
+                        pass.set_param(name, value)?;
+                    }
+                }
+            } else {
+                let kinds: Vec<PostKind> = post.iter().map(|p| p.kind).collect();
+                let mut fresh = PostChain::from_kinds(&kinds);
+                for (i, saved) in post.iter().enumerate() {
+                    *fresh.get_mut(i)? = saved.to_pass()?;
+                }
+                *chain = fresh;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The four preset slots and which one is active.
+pub struct PresetBank {
+    slots: [Preset; PRESET_SLOTS],
+    active: Option<usize>,
+}
+
+impl Default for PresetBank {
+    fn default() -> PresetBank {
+        let base = Preset {
+            name: "ALPINE".into(),
+            params: TunnelParams::default(),
+            sensitivity: 18.0,
+            performance_mode: 2,
+            glow: false,
+            smoothing: false,
+            render_scale: None,
+            upscale_filter: None,
+            line_mode: None,
+            line_cap: None,
+            face_fill: None,
+            depth_test: None,
+            scene: None,
+            color_phases: None,
+            palette_mode: None,
+            palette: None,
+            post: None,
+        };
+        let drift = Preset {
+            name: "DRIFT".into(),
+            params: TunnelParams { speed: 0.3, color_speed: 0.5, fog_near: 0.05, fog_far: 0.7, smoothing: 0.6, ..TunnelParams::default() },
+            sensitivity: 24.0,
+            performance_mode: 1,
+            smoothing: true,
+            ..base.clone()
+        };
+        let warp = Preset {
+            name: "WARP".into(),
+            params: TunnelParams { speed: 1.6, color_speed: 2.0, saturation_boost: 7.0, perspective_offset_x: 0.0, perspective_offset_y: 0.0, ..TunnelParams::default() },
+            sensitivity: 10.0,
+            ..base.clone()
+        };
+        let beam = Preset {
+            name: "BEAM".into(),
+            params: TunnelParams { mesh_thickness: 2.0, beam_add: 1.2, outer_glow_opacity: 0.85, ..TunnelParams::default() },
+            performance_mode: 1,
+            glow: true,
+            smoothing: true,
+            ..base.clone()
+        };
+        PresetBank { slots: [base, drift, warp, beam], active: Some(0) }
+    }
+}
+
+impl PresetBank {
+    pub fn get(&self, slot: usize) -> Option<&Preset> {
+        self.slots.get(slot)
+    }
+
+    /// Slot whose preset was last recalled or stored.
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+
+    /// Marks no slot active, e.g. after loading a look from outside the bank.
+    pub fn clear_active(&mut self) {
+        self.active = None;
+    }
+
+    /// Applies the preset in `slot` and marks it active.
+    pub fn recall(&mut self, slot: usize, renderer: &mut Renderer) -> Result<(), String> {
+        let preset = self.slots.get(slot).ok_or_else(|| slot_error(slot))?;
+        preset.apply(renderer)?;
+        self.active = Some(slot);
+        Ok(())
+    }
+
+    /// Stores `preset` in `slot` and marks it active.
+    pub fn store(&mut self, slot: usize, preset: Preset) -> Result<(), String> {
+        preset.validate()?;
+        let target = self.slots.get_mut(slot).ok_or_else(|| slot_error(slot))?;
+        *target = preset;
+        self.active = Some(slot);
+        Ok(())
+    }
+}
+
+fn post_pass_from_json(v: &Value) -> Result<PresetPass, String> {
+    let entry = v.as_object().ok_or("each \"post\" entry must be an object")?;
+    if let Some(key) = entry.keys().find(|k| !POST_PASS_KEYS.contains(&k.as_str())) {
+        return Err(format!("unknown post pass key {:?}", key));
+    }
+    let kind = entry.get("kind").and_then(Value::as_str).ok_or("each \"post\" entry needs a \"kind\" string")?;
+    let mut pass = PostPass::new(PostKind::from_name(kind)?);
+    if let Some(v) = entry.get("enabled") {
+        pass.set_enabled(v.as_bool().ok_or("post pass \"enabled\" must be true or false")?);
+    }
+    if let Some(v) = entry.get("params") {
+        let map = v.as_object().ok_or("post pass \"params\" must be an object")?;
+        for (name, v) in map {
+            let value = v.as_f64().ok_or_else(|| format!("{} parameter {:?} must be a number", kind, name))?;
+            pass.set_param(name, value as f32)?;
+        }
+    }
+    Ok(PresetPass::from_pass(&pass))
+}
+
+// Writes f32 values with their shortest decimal form instead of the widened f64 digits
+fn number(v: f32) -> Value {
+    if v.fract() == 0.0 && v.abs() < 1e9 {
+        return (v as i64).into();
+    }
+    v.to_string().parse::<f64>().map(Value::from).unwrap_or(Value::Null)
+}
+
+fn slot_error(slot: usize) -> String {
+    format!("preset slot must be 0..{}, got {}", PRESET_SLOTS - 1, slot)
+}
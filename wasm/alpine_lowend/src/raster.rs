@@ -0,0 +1,376 @@
+//! Rasterizers scenes draw with: lines, wide lines and convex faces, clipped
+//! to a band of rows and optionally depth-tested.
+
+use crate::renderer::LineCap;
+
+/// Rows `top..top + rows` of a `w` x `h` RGBA frame, with the matching rows of
+/// the optional depth buffer. Drawing clips to the band, so bands can be drawn
+/// separately, in any order or in parallel, and still match drawing the whole frame.
+pub struct Canvas<'a> {
+    pub buf: &'a mut [u8],
+    pub depth: Option<&'a mut [f32]>,
+    pub w: usize,
+    pub h: usize,
+    pub top: usize,
+    pub rows: usize,
+}
+
+impl<'a> Canvas<'a> {
+    /// Splits a whole frame into bands of `rows` rows, the last one possibly shorter.
+    pub fn bands(buf: &'a mut [u8], depth: Option<&'a mut [f32]>, w: usize, h: usize, rows: usize) -> Vec<Canvas<'a>> {
+        if w == 0 || h == 0 {
+            return Vec::new();
+        }
+        let rows = rows.max(1);
+        let mut depth_bands = depth.map(|d| d.chunks_mut(w * rows));
+        buf.chunks_mut(w * 4 * rows)
+            .enumerate()
+            .map(|(i, buf)| Canvas { rows: buf.len() / (w * 4), buf, depth: depth_bands.as_mut().and_then(|d| d.next()), w, h, top: i * rows })
+            .collect()
+    }
+
+    // Index of pixel (x, y) within the band; y must lie in it
+    #[inline(always)]
+    fn pixel(&self, x: usize, y: usize) -> usize {
+        (y - self.top) * self.w + x
+    }
+
+    #[inline(always)]
+    fn has_row(&self, y: i32) -> bool {
+        y >= self.top as i32 && y < (self.top + self.rows) as i32
+    }
+}
+
+// Optimized line drawing with fewer boundary checks
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_line(c: &mut Canvas, x1: i32, y1: i32, z1: f32, x2: i32, y2: i32, z2: f32, r: u8, g: u8, b: u8) {
+    let w_i32 = c.w as i32;
+    let (top, bottom) = (c.top as i32, (c.top + c.rows) as i32);
+    
+    // Early exit if line is completely outside
+    if (x1 < 0 && x2 < 0) || (x1 >= w_i32 && x2 >= w_i32) ||
+       (y1 < top && y2 < top) || (y1 >= bottom && y2 >= bottom) {
+        return;
+    }
+    
+    let mut x0 = x1;
+    let mut y0 = y1;
+    let dx = (x2 - x1).abs();
+    let dy = (y2 - y1).abs();
+    let sx = if x1 < x2 { 1 } else { -1 };
+    let sy = if y1 < y2 { 1 } else { -1 };
+    let mut err = dx - dy;
+    // Depth steps once per pixel along the major axis
+    let z_step = (z2 - z1) / dx.max(dy).max(1) as f32;
+    let mut z = z1;
+    
+    loop {
+        if x0 >= 0 && x0 < w_i32 && y0 >= top && y0 < bottom {
+            let pixel = c.pixel(x0 as usize, y0 as usize);
+            if depth_pass(&mut c.depth, pixel, z, true) {
+                let i = pixel * 4;
+                unsafe {
+                    // Use unchecked access for performance (we already bounds checked)
+                    *c.buf.get_unchecked_mut(i) = r;
+                    *c.buf.get_unchecked_mut(i + 1) = g;
+                    *c.buf.get_unchecked_mut(i + 2) = b;
+                    *c.buf.get_unchecked_mut(i + 3) = 255;
+                }
+            }
+        }
+        if x0 == x2 && y0 == y2 { break; }
+        z += z_step;
+        let e2 = 2 * err;
+        if e2 > -dy { err -= dy; x0 += sx; }
+        if e2 < dx { err += dx; y0 += sy; }
+    }
+}
+
+// Depth test against the optional per-pixel buffer (smaller is nearer). Ties
+// pass, so equal-depth primitives keep their draw order.
+#[inline(always)]
+fn depth_pass(depth: &mut Option<&mut [f32]>, pixel: usize, z: f32, write: bool) -> bool {
+    match depth {
+        None => true,
+        Some(buf) => {
+            let stored = &mut buf[pixel];
+            if z > *stored {
+                return false;
+            }
+            if write {
+                *stored = z;
+            }
+            true
+        }
+    }
+}
+
+// How anti-aliased pixels combine with what is already in the buffer
+#[derive(Clone, Copy)]
+pub(crate) enum Blend {
+    Add,
+    Max,
+}
+
+#[inline(always)]
+fn blend_pixel(buf: &mut [u8], i: usize, r: u8, g: u8, b: u8, coverage: f32, blend: Blend) {
+    let cover = |c: u8| (c as f32 * coverage) as u8;
+    let px = &mut buf[i..i + 4];
+    match blend {
+        Blend::Add => {
+            px[0] = px[0].saturating_add(cover(r));
+            px[1] = px[1].saturating_add(cover(g));
+            px[2] = px[2].saturating_add(cover(b));
+        }
+        Blend::Max => {
+            px[0] = px[0].max(cover(r));
+            px[1] = px[1].max(cover(g));
+            px[2] = px[2].max(cover(b));
+        }
+    }
+    px[3] = 255;
+}
+
+// Wu's line: each step along the major axis splits the color between the two
+// pixels straddling the exact position. Endpoints are sub-pixel, pixel centers
+// sit on integer coordinates.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_line_aa(c: &mut Canvas, x1: f32, y1: f32, z1: f32, x2: f32, y2: f32, z2: f32, r: u8, g: u8, b: u8, blend: Blend) {
+    let (w, h) = (c.w, c.h);
+    let (wf, hf) = (w as f32, h as f32);
+    // Endpoints snap half a pixel along the line, so a band can be touched
+    // by lines up to two rows beyond it
+    let (top, bottom) = (c.top as f32 - 2.0, (c.top + c.rows) as f32 + 2.0);
+    if !(x1.is_finite() && y1.is_finite() && x2.is_finite() && y2.is_finite())
+        || (x1 < -1.0 && x2 < -1.0) || (x1 > wf && x2 > wf)
+        || (y1 < -1.0 && y2 < -1.0) || (y1 > hf && y2 > hf)
+        || (y1 < top && y2 < top) || (y1 > bottom && y2 > bottom) {
+        return;
+    }
+    let steep = (y2 - y1).abs() > (x2 - x1).abs();
+    // Work in (major, minor) coordinates running toward +major
+    let (mut u0, mut v0, mut u1, mut v1) = if steep { (y1, x1, y2, x2) } else { (x1, y1, x2, y2) };
+    let (mut z0, mut z1) = (z1, z2);
+    if u0 > u1 {
+        std::mem::swap(&mut u0, &mut u1);
+        std::mem::swap(&mut v0, &mut v1);
+        std::mem::swap(&mut z0, &mut z1);
+    }
+    let major_len = if steep { h } else { w } as i32;
+    let gradient = if u1 - u0 > 0.0 { (v1 - v0) / (u1 - u0) } else { 1.0 };
+    let z_gradient = if u1 - u0 > 0.0 { (z1 - z0) / (u1 - u0) } else { 0.0 };
+    let mut plot = |u: i32, v: i32, coverage: f32| {
+        let (x, y) = if steep { (v, u) } else { (u, v) };
+        if coverage > 0.0 && x >= 0 && (x as usize) < w && c.has_row(y) {
+            let pixel = c.pixel(x as usize, y as usize);
+            // Only the pixel nearer the exact line claims the depth
+            let z = z0 + z_gradient * (u as f32 - u0);
+            if depth_pass(&mut c.depth, pixel, z, coverage >= 0.5) {
+                blend_pixel(c.buf, pixel * 4, r, g, b, coverage, blend);
+            }
+        }
+    };
+    let mut endpoint = |u: f32, v: f32, gap: f32| -> i32 {
+        let u_end = u.round();
+        let v_end = v + gradient * (u_end - u);
+        let (ui, vi) = (u_end as i32, v_end.floor() as i32);
+        let f = v_end - v_end.floor();
+        plot(ui, vi, (1.0 - f) * gap);
+        plot(ui, vi + 1, f * gap);
+        ui
+    };
+    let start = endpoint(u0, v0, 1.0 - (u0 + 0.5).fract());
+    let end = endpoint(u1, v1, (u1 + 0.5).fract());
+    // Only walk the span that lands inside the buffer
+    let first = (start + 1).max(0);
+    let last = end.min(major_len);
+    let mut v = v0 + gradient * (first as f32 - u0);
+    for u in first..last {
+        let vi = v.floor();
+        let f = v - vi;
+        plot(u, vi as i32, 1.0 - f);
+        plot(u, vi as i32 + 1, f);
+        v += gradient;
+    }
+}
+
+
+// Line of any width with round or square caps. Each pixel is covered by how far
+// its center lies inside the shape: blended when anti-aliased, otherwise
+// written opaque when the center is inside.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_wide_line(c: &mut Canvas, x1: f32, y1: f32, z1: f32, x2: f32, y2: f32, z2: f32, width: f32, cap: LineCap, r: u8, g: u8, b: u8, blend: Option<Blend>) {
+    let w = c.w;
+    if w == 0 || c.rows == 0 || !(x1.is_finite() && y1.is_finite() && x2.is_finite() && y2.is_finite() && width > 0.0) {
+        return;
+    }
+    let hw = width * 0.5;
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let len = (dx * dx + dy * dy).sqrt();
+    let (ux, uy) = if len > 1e-6 { (dx / len, dy / len) } else { (1.0, 0.0) };
+    // Pixels further than this from the center line cannot be covered
+    let reach = hw + 1.0;
+    let bx0 = (x1.min(x2) - reach).floor().max(0.0);
+    let bx1 = (x1.max(x2) + reach).ceil().min(w as f32 - 1.0);
+    let by0 = (y1.min(y2) - reach).floor().max(c.top as f32);
+    let by1 = (y1.max(y2) + reach).ceil().min((c.top + c.rows) as f32 - 1.0);
+    if bx0 > bx1 || by0 > by1 {
+        return;
+    }
+    for py in by0 as usize..=by1 as usize {
+        let qy = py as f32 - y1;
+        // Columns of this row within `reach` of the infinite center line
+        let (lo, hi) = if uy.abs() > 1e-3 {
+            let c = x1 + qy * ux / uy;
+            let s = (reach / uy).abs();
+            ((c - s).floor().max(bx0), (c + s).ceil().min(bx1))
+        } else {
+            (bx0, bx1)
+        };
+        if lo > hi {
+            continue;
+        }
+        for px in lo as usize..=hi as usize {
+            let qx = px as f32 - x1;
+            let t = qx * ux + qy * uy;
+            // Signed distance from the pixel center to the outline
+            let sd = match cap {
+                LineCap::Round => {
+                    let tc = t.clamp(0.0, len);
+                    let (ex, ey) = (qx - ux * tc, qy - uy * tc);
+                    (ex * ex + ey * ey).sqrt() - hw
+                }
+                LineCap::Square => {
+                    let n = (qx * uy - qy * ux).abs();
+                    (n - hw).max(-t - hw).max(t - len - hw)
+                }
+            };
+            let coverage = match blend {
+                Some(_) => (0.5 - sd).min(1.0),
+                None if sd <= 0.0 => 1.0,
+                None => 0.0,
+            };
+            if coverage <= 0.0 {
+                continue;
+            }
+            let z = if len > 1e-6 { z1 + (z2 - z1) * (t / len).clamp(0.0, 1.0) } else { z1 };
+            let pixel = c.pixel(px, py);
+            if !depth_pass(&mut c.depth, pixel, z, coverage >= 0.5) {
+                continue;
+            }
+            let i = pixel * 4;
+            match blend {
+                Some(blend) => blend_pixel(c.buf, i, r, g, b, coverage, blend),
+                None => c.buf[i..i + 4].copy_from_slice(&[r, g, b, 255]),
+            }
+        }
+    }
+}
+
+// Scanline fill of a convex polygon of (x, y, z) corners, alpha-blended over
+// the buffer. A pixel is covered when its center is inside; spans are half-open
+// on both axes so faces sharing an edge never blend the same pixel twice.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_convex(c: &mut Canvas, pts: &[(f32, f32, f32)], r: u8, g: u8, b: u8, alpha: f32) {
+    let w = c.w;
+    if pts.len() < 3 || alpha <= 0.0 || pts.iter().any(|p| !(p.0.is_finite() && p.1.is_finite())) {
+        return;
+    }
+    let a = (alpha.min(1.0) * 256.0) as u32;
+    let (ymin, ymax) = pts.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| (lo.min(p.1), hi.max(p.1)));
+    let y0 = (ymin.ceil() as i32).max(c.top as i32);
+    let y1 = (ymax.ceil() as i32).min((c.top + c.rows) as i32);
+    for y in y0..y1 {
+        let yf = y as f32;
+        // Span ends with the depth interpolated along their edges
+        let (mut xl, mut zl, mut xr, mut zr) = (f32::INFINITY, 0.0, f32::NEG_INFINITY, 0.0);
+        for (i, &(ax, ay, az)) in pts.iter().enumerate() {
+            let (bx, by, bz) = pts[(i + 1) % pts.len()];
+            if (ay <= yf && yf < by) || (by <= yf && yf < ay) {
+                let t = (yf - ay) / (by - ay);
+                let x = ax + t * (bx - ax);
+                let z = az + t * (bz - az);
+                if x < xl {
+                    (xl, zl) = (x, z);
+                }
+                if x > xr {
+                    (xr, zr) = (x, z);
+                }
+            }
+        }
+        if xl >= xr {
+            continue;
+        }
+        let x0 = (xl.ceil() as i32).clamp(0, w as i32) as usize;
+        let x1 = (xr.ceil() as i32).clamp(0, w as i32) as usize;
+        let z_step = (zr - zl) / (xr - xl);
+        let row = c.pixel(0, y as usize);
+        for x in x0..x1 {
+            if !depth_pass(&mut c.depth, row + x, zl + (x as f32 - xl) * z_step, true) {
+                continue;
+            }
+            let px = &mut c.buf[(row + x) * 4..(row + x) * 4 + 4];
+            px[0] = ((r as u32 * a + px[0] as u32 * (256 - a)) >> 8) as u8;
+            px[1] = ((g as u32 * a + px[1] as u32 * (256 - a)) >> 8) as u8;
+            px[2] = ((b as u32 * a + px[2] as u32 * (256 - a)) >> 8) as u8;
+            px[3] = 255;
+        }
+    }
+}
+
+// Nearest-neighbour upscale of an RGBA buffer into `dst`, output rows `top..`
+// of a `dw` x `dh` frame; rows that map to the same source row are copied from
+// the previous output row
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn upscale_nearest(src: &[u8], sw: usize, sh: usize, dst: &mut [u8], dw: usize, dh: usize, top: usize) {
+    let xmap: Vec<usize> = (0..dw).map(|x| (x * sw / dw) * 4).collect();
+    let stride = dw * 4;
+    let mut prev_sy = usize::MAX;
+    for y in top..top + dst.len() / stride {
+        let sy = y * sh / dh;
+        let out = (y - top) * stride;
+        if sy == prev_sy {
+            dst.copy_within(out - stride..out, out);
+            continue;
+        }
+        prev_sy = sy;
+        let row = &src[sy * sw * 4..(sy + 1) * sw * 4];
+        for (px, &sx) in dst[out..out + stride].chunks_exact_mut(4).zip(&xmap) {
+            px.copy_from_slice(&row[sx..sx + 4]);
+        }
+    }
+}
+
+// Bilinear upscale of an RGBA buffer, sampling at pixel centers with 8-bit
+// weights; `dst` holds output rows `top..` of a `dw` x `dh` frame
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn upscale_bilinear(src: &[u8], sw: usize, sh: usize, dst: &mut [u8], dw: usize, dh: usize, top: usize) {
+    // Per output coordinate: first source index, next source index, weight of the next (0-256)
+    let taps = |dn: usize, sn: usize| -> Vec<(usize, usize, u32)> {
+        (0..dn)
+            .map(|d| {
+                let pos = ((d as f32 + 0.5) * sn as f32 / dn as f32 - 0.5).max(0.0);
+                let i0 = (pos as usize).min(sn - 1);
+                let i1 = (i0 + 1).min(sn - 1);
+                (i0, i1, ((pos - i0 as f32) * 256.0) as u32)
+            })
+            .collect()
+    };
+    let xt = taps(dw, sw);
+    let yt = taps(dh, sh);
+    let sstride = sw * 4;
+    for (out, &(y0, y1, fy)) in dst.chunks_exact_mut(dw * 4).zip(&yt[top..]) {
+        let r0 = &src[y0 * sstride..(y0 + 1) * sstride];
+        let r1 = &src[y1 * sstride..(y1 + 1) * sstride];
+        for (px, &(x0, x1, fx)) in out.chunks_exact_mut(4).zip(&xt) {
+            let (a, b) = (x0 * 4, x1 * 4);
+            for c in 0..3 {
+                let top = r0[a + c] as u32 * (256 - fx) + r0[b + c] as u32 * fx;
+                let bottom = r1[a + c] as u32 * (256 - fx) + r1[b + c] as u32 * fx;
+                px[c] = ((top * (256 - fy) + bottom * fy) >> 16) as u8;
+            }
+            px[3] = 255;
+        }
+    }
+}
@@ -0,0 +1,858 @@
+//! Platform-independent renderer: spectrum in, RGBA framebuffer out. The
+//! visuals come from the active `Scene`; the tunnel is the default one.
+//! Nothing in here touches the DOM or JS, so it builds and runs natively.
+
+use crate::palette::{DepthGradient, Palette, PaletteMode};
+use crate::params::TunnelParams;
+use crate::pixel;
+use crate::rng::Rng;
+use crate::post::{PostChain, PostInput, PostKind, PostScratch};
+use crate::raster::{upscale_bilinear, upscale_nearest};
+use crate::scene::{AudioFeatures, DrawTarget, FrameInput, Scene, SceneFactory, SceneRegistry, SceneSetup};
+use crate::tempo::TempoTracker;
+use crate::tiles;
+use crate::transient::{DrumKind, TransientDetector};
+use crate::tunnel::Tunnel;
+
+const PI2: f32 = std::f32::consts::PI * 2.0;
+
+/// Refresh rate the per-frame animation rates were tuned at; other frame
+/// intervals scale every integrator and decay to match it.
+pub const REFERENCE_HZ: f32 = 60.0;
+
+// Longest catch-up a single frame may apply (matches the 0.25 s interval cap)
+const MAX_STEPS: f32 = 0.25 * REFERENCE_HZ;
+
+// Gradient loops per reference frame at color_speed 1: one every 10 s
+const PALETTE_CYCLE_RATE: f32 = 1.0 / 600.0;
+
+/// Seed a new `Renderer` starts from.
+pub const DEFAULT_SEED: u64 = 0x414C_5049_4E45; // "ALPINE"
+
+/// How a frame rendered below output resolution is scaled up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpscaleFilter {
+    /// Blocky pixel look, cheapest.
+    Nearest,
+    /// Soft interpolation between internal pixels.
+    Bilinear,
+}
+
+impl UpscaleFilter {
+    pub fn from_name(name: &str) -> Result<UpscaleFilter, String> {
+        match name {
+            "nearest" => Ok(UpscaleFilter::Nearest),
+            "bilinear" => Ok(UpscaleFilter::Bilinear),
+            _ => Err(format!("unknown upscale filter {:?} (expected nearest or bilinear)", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            UpscaleFilter::Nearest => "nearest",
+            UpscaleFilter::Bilinear => "bilinear",
+        }
+    }
+}
+
+/// How mesh lines are rasterized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineMode {
+    /// Opaque Bresenham pixels, cheapest.
+    Aliased,
+    /// Anti-aliased; overlapping lines add up and glow where they cross.
+    SmoothAdd,
+    /// Anti-aliased; overlapping lines keep the brighter value.
+    SmoothMax,
+}
+
+impl LineMode {
+    pub fn from_name(name: &str) -> Result<LineMode, String> {
+        match name {
+            "aliased" => Ok(LineMode::Aliased),
+            "smooth-add" => Ok(LineMode::SmoothAdd),
+            "smooth-max" => Ok(LineMode::SmoothMax),
+            _ => Err(format!("unknown line mode {:?} (expected aliased, smooth-add or smooth-max)", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineMode::Aliased => "aliased",
+            LineMode::SmoothAdd => "smooth-add",
+            LineMode::SmoothMax => "smooth-max",
+        }
+    }
+}
+
+/// End shape of mesh lines wider than one pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    /// Half-disc ends; neighbouring segments join without gaps.
+    Round,
+    /// Ends extended by half the width, for a blockier look.
+    Square,
+}
+
+impl LineCap {
+    pub fn from_name(name: &str) -> Result<LineCap, String> {
+        match name {
+            "round" => Ok(LineCap::Round),
+            "square" => Ok(LineCap::Square),
+            _ => Err(format!("unknown line cap {:?} (expected round or square)", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineCap::Round => "round",
+            LineCap::Square => "square",
+        }
+    }
+}
+
+/// Whether the quads between mesh lines are filled, and how they are shaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaceFill {
+    /// Wireframe only.
+    None,
+    /// One brightness per face orientation: inner faces brightest, tube wall darkest.
+    Flat,
+    /// Flat shading that also darkens with distance.
+    Depth,
+}
+
+impl FaceFill {
+    pub fn from_name(name: &str) -> Result<FaceFill, String> {
+        match name {
+            "none" => Ok(FaceFill::None),
+            "flat" => Ok(FaceFill::Flat),
+            "depth" => Ok(FaceFill::Depth),
+            _ => Err(format!("unknown face fill {:?} (expected none, flat or depth)", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FaceFill::None => "none",
+            FaceFill::Flat => "flat",
+            FaceFill::Depth => "depth",
+        }
+    }
+}
+
+/// Renderer state: the active scene, framebuffers, audio state and color phases.
+pub struct Renderer {
+    width: u32,
+    height: u32,
+    vu: Vec<u8>,
+    vu_active_bins: usize,
+    scenes: SceneRegistry,
+    scene: Box<dyn Scene>,
+    scene_name: String,
+    // Set once the first resize has initialized the scene
+    scene_ready: bool,
+    frame_buffer: Vec<u8>,
+    // Internal-resolution target when render_scale < 1; the passes below run at that size too
+    scene_buffer: Vec<u8>,
+    post: PostChain,
+    scratch: PostScratch,
+    mouse_x: f32,
+    mouse_y: f32,
+    mouse_active: bool,
+    mouse_down: bool,
+    color_invert_value: f32,
+    beat_env: f32,
+    beat_boost: f32,
+    tempo: TempoTracker,
+    transients: TransientDetector,
+    // Decaying responses to the latest kick and hat hits, 0..1
+    kick_push: f32,
+    hat_flash: f32,
+    // Seconds between render calls, as set by the host
+    frame_interval: f32,
+    // Time that passed during skipped frames, caught up on the next rendered one
+    skipped_time: f32,
+    // Whether the last render call was skipped
+    frame_skipped: bool,
+    rgb1_r: f32,
+    rgb1_g: f32,
+    rgb1_b: f32,
+    rgb2_r: f32,
+    rgb2_g: f32,
+    rgb2_b: f32,
+    palette: Palette,
+    palette_mode: PaletteMode,
+    // Position on the gradient, 0..1, advanced by color_speed outside the sine mode
+    palette_phase: f32,
+    // Eased energy that picks the position in the energy mode
+    palette_energy: f32,
+    frequency_damp: f32,
+    params: TunnelParams,
+    // Source of every random choice: scene geometry and color phases
+    seed: u64,
+    rng: Rng,
+
+    // Performance flags - can be configured
+    performance_mode: u8,
+    low_end_mode: bool,
+    render_scale: f32, // 0.5 for half resolution
+    upscale_filter: UpscaleFilter,
+    line_mode: LineMode,
+    line_cap: LineCap,
+    face_fill: FaceFill,
+    // Per-pixel nearest z at internal resolution; empty while the test is off
+    depth_test: bool,
+    depth_buffer: Vec<f32>,
+    tile_rows: usize, // rows per parallel band, 0 to size them from the thread count
+    skip_frame_count: u8, // frames skipped after each rendered one
+    skip_phase: u8,
+}
+
+impl Renderer {
+    /// A renderer seeded with `DEFAULT_SEED`; see `set_seed`.
+    pub fn new(width: u32, height: u32) -> Renderer {
+        let mut r = Renderer {
+            width: 0,
+            height: 0,
+            vu: vec![0u8; 8192],
+            vu_active_bins: 0,
+            scenes: SceneRegistry::default(),
+            scene: Box::new(Tunnel::default()),
+            scene_name: "tunnel".to_string(),
+            scene_ready: false,
+            frame_buffer: Vec::new(),
+            scene_buffer: Vec::new(),
+            post: PostChain::default(),
+            scratch: PostScratch::default(),
+            mouse_x: 0.0,
+            mouse_y: 0.0,
+            mouse_active: false,
+            mouse_down: false,
+            color_invert_value: 0.0,
+            beat_env: 0.0,
+            beat_boost: 0.0,
+            tempo: TempoTracker::default(),
+            transients: TransientDetector::default(),
+            kick_push: 0.0,
+            hat_flash: 0.0,
+            frame_interval: 1.0 / 60.0,
+            skipped_time: 0.0,
+            frame_skipped: false,
+            rgb1_r: 0.0,
+            rgb1_g: 0.0,
+            rgb1_b: 0.0,
+            rgb2_r: 0.0,
+            rgb2_g: 0.0,
+            rgb2_b: 0.0,
+            palette: Palette::default(),
+            palette_mode: PaletteMode::Sine,
+            palette_phase: 0.0,
+            palette_energy: 0.0,
+            frequency_damp: 18.0,
+            params: TunnelParams::default(),
+            seed: DEFAULT_SEED,
+            rng: Rng::new(DEFAULT_SEED),
+            performance_mode: 2,
+            low_end_mode: false,
+            render_scale: 1.0,
+            upscale_filter: UpscaleFilter::Bilinear,
+            line_mode: LineMode::Aliased,
+            line_cap: LineCap::Round,
+            face_fill: FaceFill::None,
+            depth_test: false,
+            depth_buffer: Vec::new(),
+            tile_rows: 0,
+            skip_frame_count: 0,
+            skip_phase: 0,
+        };
+        if width > 0 && height > 0 {
+            r.resize(width, height);
+        }
+        r
+    }
+
+    /// Restarts the random sequence from `seed` and re-derives everything drawn
+    /// from it (color phases and the scene). The same seed, size, settings and
+    /// spectra in the same order give byte-identical frames.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+        self.seed_color_phases();
+        self.palette_phase = 0.0;
+        if self.scene_ready {
+            self.init_scene();
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn set_low_end_mode(&mut self, enabled: bool) {
+        self.set_low_end_geometry(enabled);
+        if enabled {
+            self.set_glow(false);
+            self.set_smoothing(false);
+        }
+    }
+
+    pub fn set_sensitivity(&mut self, value: f32) {
+        self.frequency_damp = value.max(1.0);
+    }
+
+    pub fn sensitivity(&self) -> f32 {
+        self.frequency_damp
+    }
+
+    pub fn set_glow(&mut self, enabled: bool) {
+        self.post.set_enabled(PostKind::Glow, enabled);
+    }
+
+    pub fn glow_enabled(&self) -> bool {
+        self.post.enabled(PostKind::Glow)
+    }
+
+    pub fn set_smoothing(&mut self, enabled: bool) {
+        self.post.set_enabled(PostKind::Smoothing, enabled);
+    }
+
+    pub fn smoothing_enabled(&self) -> bool {
+        self.post.enabled(PostKind::Smoothing)
+    }
+
+    /// Post-processing passes in the order they run after the scene draws.
+    pub fn post_chain(&self) -> &PostChain {
+        &self.post
+    }
+
+    /// Add, remove, reorder or tune passes; `set_glow`/`set_smoothing` toggle
+    /// every pass of that kind.
+    pub fn post_chain_mut(&mut self) -> &mut PostChain {
+        &mut self.post
+    }
+
+    /// Current phases of the two color oscillators, as (r, g, b, r2, g2, b2).
+    pub fn color_phases(&self) -> [f32; 6] {
+        [self.rgb1_r, self.rgb1_g, self.rgb1_b, self.rgb2_r, self.rgb2_g, self.rgb2_b]
+    }
+
+    pub fn set_color_phases(&mut self, phases: [f32; 6]) {
+        [self.rgb1_r, self.rgb1_g, self.rgb1_b, self.rgb2_r, self.rgb2_g, self.rgb2_b] = phases;
+    }
+
+    /// Gradient the time, energy and depth palette modes pick colors from.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// What picks the line colors; `Sine` (the default) ignores the palette.
+    pub fn set_palette_mode(&mut self, mode: PaletteMode) {
+        self.palette_mode = mode;
+    }
+
+    pub fn palette_mode(&self) -> PaletteMode {
+        self.palette_mode
+    }
+
+    /// Fraction of the output resolution the tunnel and its effects render at
+    /// (0.25..=1); the result is upscaled so `frame()` stays output-sized.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        let scale = if scale.is_nan() { 1.0 } else { scale.clamp(0.25, 1.0) };
+        if scale != self.render_scale {
+            self.render_scale = scale;
+            self.resize_internal_buffers();
+        }
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    pub fn set_upscale_filter(&mut self, filter: UpscaleFilter) {
+        self.upscale_filter = filter;
+    }
+
+    pub fn upscale_filter(&self) -> UpscaleFilter {
+        self.upscale_filter
+    }
+
+    /// Line rasterization for the mesh; low-end mode always uses `Aliased`.
+    pub fn set_line_mode(&mut self, mode: LineMode) {
+        self.line_mode = mode;
+    }
+
+    pub fn line_mode(&self) -> LineMode {
+        self.line_mode
+    }
+
+    /// Cap style of lines wider than one pixel (see the `mesh_thickness` param).
+    pub fn set_line_cap(&mut self, cap: LineCap) {
+        self.line_cap = cap;
+    }
+
+    pub fn line_cap(&self) -> LineCap {
+        self.line_cap
+    }
+
+    /// Solid faces under the mesh lines (see the `face_alpha`/`face_brightness`
+    /// params); low-end mode always draws wireframe only.
+    pub fn set_face_fill(&mut self, fill: FaceFill) {
+        self.face_fill = fill;
+    }
+
+    pub fn face_fill(&self) -> FaceFill {
+        self.face_fill
+    }
+
+    /// Tests lines and faces against a per-pixel depth buffer so nearer geometry
+    /// is never painted over by farther rows; low-end mode skips it.
+    pub fn set_depth_test(&mut self, enabled: bool) {
+        if enabled != self.depth_test {
+            self.depth_test = enabled;
+            self.resize_internal_buffers();
+        }
+    }
+
+    pub fn depth_test_enabled(&self) -> bool {
+        self.depth_test
+    }
+
+    /// Rows per band when drawing and post-processing in bands; 0 (the default)
+    /// picks a size from the worker count, which is one band per frame in the
+    /// single-threaded build. The output is the same for any band size.
+    pub fn set_tile_rows(&mut self, rows: usize) {
+        self.tile_rows = rows;
+    }
+
+    pub fn tile_rows(&self) -> usize {
+        self.tile_rows
+    }
+
+    /// Switches to the registered scene `name` (see `scene_names`). The new
+    /// scene starts fresh; audio state, colors and post-processing carry over.
+    pub fn set_scene(&mut self, name: &str) -> Result<(), String> {
+        let scene = self.scenes.create(name)?;
+        self.scene = scene;
+        self.scene_name = name.to_string();
+        if self.scene_ready {
+            self.init_scene();
+        }
+        Ok(())
+    }
+
+    pub fn scene_name(&self) -> &str {
+        &self.scene_name
+    }
+
+    pub fn scene_names(&self) -> Vec<&'static str> {
+        self.scenes.names()
+    }
+
+    /// Makes a scene available to `set_scene`, replacing any with the same name.
+    pub fn register_scene(&mut self, name: &'static str, factory: SceneFactory) {
+        self.scenes.register(name, factory);
+    }
+
+    /// Resolution the scene is rasterized at before upscaling.
+    pub fn internal_size(&self) -> (u32, u32) {
+        if self.width == 0 || self.height == 0 {
+            return (0, 0);
+        }
+        let scaled = |n: u32| ((n as f32 * self.render_scale) as u32).clamp(1, n);
+        (scaled(self.width), scaled(self.height))
+    }
+
+    fn resize_internal_buffers(&mut self) {
+        let (iw, ih) = self.internal_size();
+        let needed = iw as usize * ih as usize * 4;
+        let scene = if (iw, ih) == (self.width, self.height) { 0 } else { needed };
+        self.scene_buffer.resize(scene, 0);
+        self.post.reset();
+        self.scratch.refit(self.post.glow_scratch_len(iw as usize, ih as usize));
+        let depth = if self.depth_test { iw as usize * ih as usize } else { 0 };
+        self.depth_buffer.resize(depth, f32::INFINITY);
+    }
+
+    /// Renders only every `(skip + 1)`th call to `render`; the frame in between
+    /// is left as it was.
+    pub fn set_frame_skip(&mut self, skip: u8) {
+        self.skip_frame_count = skip;
+        self.skip_phase = 0;
+    }
+
+    pub fn frame_skip(&self) -> u8 {
+        self.skip_frame_count
+    }
+
+    /// True if the last `render` call was one the frame skip left out.
+    pub fn frame_skipped(&self) -> bool {
+        self.frame_skipped
+    }
+
+    pub fn set_performance_mode(&mut self, mode: u8) {
+        match mode {
+            1 => { // Balanced - disable only heavy effects
+                self.set_low_end_geometry(false);
+                self.set_glow(false);
+                self.set_smoothing(true);
+            }
+            2 => { // Performance default - also enable low-end geometry
+                self.set_low_end_geometry(false);
+                self.set_glow(false);
+                self.set_smoothing(false);
+            }
+            _ => { // Maximum performance
+                self.set_low_end_geometry(true);
+                self.set_glow(false);
+                self.set_smoothing(false);
+            }
+        }
+        self.performance_mode = mode.clamp(1, 3);
+    }
+
+    pub fn performance_mode(&self) -> u8 {
+        self.performance_mode
+    }
+
+    // Low-end mode uses its own segment count and ring spacing
+    fn set_low_end_geometry(&mut self, enabled: bool) {
+        if self.low_end_mode != enabled {
+            self.low_end_mode = enabled;
+            if self.scene_ready {
+                self.init_scene();
+            }
+        }
+    }
+
+    pub fn params(&self) -> &TunnelParams {
+        &self.params
+    }
+
+    /// Replaces all parameters; re-initializes the scene if the geometry changed.
+    pub fn set_params(&mut self, params: TunnelParams) -> Result<(), String> {
+        params.validate()?;
+        let rebuild = self.params.geometry_differs(&params);
+        self.params = params;
+        if rebuild && self.scene_ready {
+            self.init_scene();
+        }
+        Ok(())
+    }
+
+    pub fn set_param(&mut self, name: &str, value: f32) -> Result<(), String> {
+        let mut params = self.params;
+        params.set(name, value)?;
+        self.set_params(params)
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.seed_color_phases();
+        self.init_scene();
+        let needed = (width as usize) * (height as usize) * 4;
+        self.frame_buffer.resize(needed, 0);
+        self.resize_internal_buffers();
+    }
+
+    /// Copies in spectrum magnitudes (0-255 per bin, AnalyserNode layout).
+    pub fn set_spectrum(&mut self, buf: &[u8]) {
+        let copy_len = buf.len().min(self.vu.len());
+        self.vu[..copy_len].copy_from_slice(&buf[..copy_len]);
+        self.vu_active_bins = copy_len;
+    }
+
+    /// The active part of the last spectrum passed to `set_spectrum`.
+    pub fn spectrum(&self) -> &[u8] {
+        &self.vu[..self.vu_active_bins]
+    }
+
+    /// Seconds between `render` calls (default 1/60, at most 0.25). Animation,
+    /// decays and the tempo tracker all advance by this much per call, so the
+    /// tunnel moves at the same speed at any refresh rate.
+    pub fn set_frame_interval(&mut self, seconds: f32) {
+        if seconds.is_finite() && seconds > 0.0 {
+            self.frame_interval = seconds.min(0.25);
+        }
+    }
+
+    pub fn frame_interval(&self) -> f32 {
+        self.frame_interval
+    }
+
+    pub fn tempo(&self) -> &TempoTracker {
+        &self.tempo
+    }
+
+    pub fn transients(&self) -> &TransientDetector {
+        &self.transients
+    }
+
+    pub fn transients_mut(&mut self) -> &mut TransientDetector {
+        &mut self.transients
+    }
+
+    /// Sample rate behind the spectrum, so drum bands land on the right bins.
+    pub fn set_sample_rate(&mut self, hz: f32) {
+        self.transients.set_nyquist_hz(hz / 2.0);
+    }
+
+    /// Pointer position in output pixels. While `active`, the perspective center
+    /// eases toward it (see the `mouse_steer`/`steer_inertia` params); `down`
+    /// reverses the flight and inverts colors.
+    pub fn set_mouse(&mut self, x: f32, y: f32, active: bool, down: bool) {
+        self.mouse_x = x;
+        self.mouse_y = y;
+        self.mouse_active = active;
+        self.mouse_down = down;
+    }
+
+    /// RGBA8 framebuffer, `width * height * 4` bytes.
+    pub fn frame(&self) -> &[u8] {
+        &self.frame_buffer
+    }
+
+    /// Renders one frame from the current spectrum into the RGBA framebuffer.
+    /// Each call advances the animation by `frame_interval` seconds.
+    pub fn render(&mut self) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        if w == 0 || h == 0 { return; }
+        self.tempo.push(&self.vu[..self.vu_active_bins], self.frame_interval);
+        for hit in self.transients.push(&self.vu[..self.vu_active_bins], self.frame_interval) {
+            match hit.kind {
+                DrumKind::Kick => self.kick_push = self.kick_push.max(hit.strength),
+                DrumKind::Hat => self.hat_flash = self.hat_flash.max(hit.strength),
+                DrumKind::Snare => {}
+            }
+        }
+        if self.skip_frame_count > 0 {
+            let phase = self.skip_phase;
+            self.skip_phase = if phase >= self.skip_frame_count { 0 } else { phase + 1 };
+            if phase != 0 {
+                self.skipped_time += self.frame_interval;
+                self.frame_skipped = true;
+                return;
+            }
+        }
+        self.frame_skipped = false;
+        // Reference frames elapsed since the last rendered frame
+        let steps = ((self.frame_interval + self.skipped_time) * REFERENCE_HZ).min(MAX_STEPS);
+        self.skipped_time = 0.0;
+        // Geometry stays in output coordinates; drawing maps it to the internal size
+        let (iw, ih) = self.internal_size();
+        let (iw, ih) = (iw as usize, ih as usize);
+        let direct = iw == w && ih == h;
+        let (sx, sy) = (iw as f32 / w as f32, ih as f32 / h as f32);
+        // Low-end mode forces the cheap path and skips the expensive effects
+        let low_end_mode = self.low_end_mode;
+        let line_mode = if low_end_mode { LineMode::Aliased } else { self.line_mode };
+        let face_fill = if low_end_mode { FaceFill::None } else { self.face_fill };
+        let depth_test = self.depth_test && !low_end_mode;
+        let p = self.params;
+
+        let needed = w * h * 4;
+        if self.frame_buffer.len() != needed {
+            self.frame_buffer.resize(needed, 0);
+        }
+
+        let band_rows = tiles::band_rows(ih, self.tile_rows);
+        let target_pixels = if direct { &mut self.frame_buffer } else { &mut self.scene_buffer };
+        tiles::bands(target_pixels, iw * 4, band_rows, |_, band| pixel::clear(band));
+
+        let (energy_avg, bass_avg, beat_boost) = {
+            let vu_data = &self.vu;
+            let bins = self.vu_active_bins.max(1);
+            let mut sum: u32 = 0; for v in vu_data.iter().take(bins) { sum += *v as u32; }
+            let ea = (sum as f32) / (bins as f32) / 255.0;
+            let bbins = if bins > 4 { 4 } else { bins };
+            let mut s = 0u32; for v in vu_data.iter().take(bbins){ s += *v as u32; }
+            let ba = (s as f32) / (bbins as f32) / 255.0;
+            let env_attack = 0.90f32.powf(steps);
+            let env_release = 0.98f32.powf(steps);
+            let prev = self.beat_env;
+            let env = if ba > prev { prev * env_attack + ba * (1.0 - env_attack) } else { prev * env_release + ba * (1.0 - env_release) };
+            self.beat_env = env;
+            let pulse = (ba - env - 0.02).max(0.0);
+            // Same steady state at any step: the input gain follows the decay
+            let decay = 0.90f32.powf(steps);
+            self.beat_boost = self.beat_boost * decay + pulse * 28.0 * (1.0 - decay) / (1.0 - 0.90);
+            (ea, ba, self.beat_boost.min(6.5))
+        };
+        // Tempo lock trades the bass-bump pulse for one that decays across each tracked beat
+        let beat_boost = if p.tempo_lock > 0.0 {
+            let pulse = (1.0 - self.tempo.beat_phase()).powi(4) * 6.5 * self.tempo.confidence();
+            beat_boost * (1.0 - p.tempo_lock) + pulse * p.tempo_lock
+        } else {
+            beat_boost
+        };
+        let (kick, hat) = (self.kick_push, self.hat_flash);
+        self.kick_push *= 0.85f32.powf(steps);
+        self.hat_flash *= 0.6f32.powf(steps);
+
+        let energy_ease = 1.0 - 0.9f32.powf(steps);
+        self.palette_energy += (energy_avg - self.palette_energy) * energy_ease;
+        let mut depth_gradient = None;
+        let (color1, color2) = match self.palette_mode {
+            PaletteMode::Sine => {
+                let color1 = self.get_rgb_color1(steps);
+                let color2 = limit_color(self.get_rgb_color2(steps).0, self.get_rgb_color2(steps).1, self.get_rgb_color2(steps).2, p.color_floor);
+                (color1, color2)
+            }
+            mode => {
+                self.palette_phase = (self.palette_phase + PALETTE_CYCLE_RATE * p.color_speed * steps).rem_euclid(1.0);
+                let offset = match mode {
+                    PaletteMode::Energy => self.palette_phase + self.palette_energy * p.palette_spread,
+                    _ => self.palette_phase,
+                };
+                if mode == PaletteMode::Depth {
+                    depth_gradient = Some(DepthGradient { palette: &self.palette, offset, spread: p.palette_spread, floor: p.color_floor });
+                }
+                self.palette.colors(offset, p.color_floor)
+            }
+        };
+
+        let input = FrameInput {
+            width: self.width,
+            height: self.height,
+            audio: AudioFeatures { spectrum: &self.vu, energy: energy_avg, bass: bass_avg, beat: beat_boost, kick, hat },
+            dt: steps / REFERENCE_HZ,
+            steps,
+            color1,
+            color2,
+            depth_gradient,
+            pointer: (self.mouse_x, self.mouse_y),
+            pointer_active: self.mouse_active,
+            pointer_down: self.mouse_down,
+            sensitivity: self.frequency_damp,
+            params: &p,
+            low_end: low_end_mode,
+        };
+        self.scene.update(&input);
+        let depth = if depth_test {
+            self.depth_buffer.fill(f32::INFINITY);
+            Some(&mut self.depth_buffer[..])
+        } else {
+            None
+        };
+        let mut target = DrawTarget {
+            pixels: if direct { &mut self.frame_buffer } else { &mut self.scene_buffer },
+            depth,
+            width: iw,
+            height: ih,
+            scale_x: sx,
+            scale_y: sy,
+            line_mode,
+            line_cap: self.line_cap,
+            face_fill,
+            band_rows,
+        };
+        self.scene.draw(&input, &mut target);
+
+        let invert_step = 5.0 * steps;
+        if self.mouse_down {
+            self.color_invert_value = (self.color_invert_value + invert_step).min(255.0);
+        } else {
+            self.color_invert_value = (self.color_invert_value - invert_step).max(0.0);
+        }
+
+        // Passes run at internal resolution until the first one that wants the
+        // output size; the frame is upscaled right before it (or at the end)
+        let mut post_input = PostInput {
+            energy: energy_avg,
+            bass: bass_avg,
+            steps,
+            scale: sx,
+            invert: self.color_invert_value as u8,
+            params: &p,
+            tile_rows: self.tile_rows,
+        };
+        let mut at_output = direct;
+        for pass in self.post.passes_mut() {
+            if !pass.enabled() || (low_end_mode && pass.kind().expensive()) {
+                continue;
+            }
+            if pass.kind().at_output() && !at_output {
+                upscale(self.upscale_filter, &self.scene_buffer, iw, ih, &mut self.frame_buffer, w, h, self.tile_rows);
+                at_output = true;
+                post_input.scale = 1.0;
+            }
+            if at_output {
+                pass.apply(&mut self.frame_buffer, w, h, &post_input, &mut self.scratch);
+            } else {
+                pass.apply(&mut self.scene_buffer, iw, ih, &post_input, &mut self.scratch);
+            }
+        }
+        if !at_output {
+            upscale(self.upscale_filter, &self.scene_buffer, iw, ih, &mut self.frame_buffer, w, h, self.tile_rows);
+        }
+    }
+
+    fn get_rgb_color1(&mut self, steps: f32) -> (f32, f32, f32) {
+        let k = self.params.color_speed * steps;
+        self.rgb1_r += 0.040 * k;
+        self.rgb1_g += 0.028 * k;
+        self.rgb1_b += 0.052 * k;
+        let r = self.rgb1_r.sin() + 1.0;
+        let g = self.rgb1_g.sin() + 1.0;
+        let b = self.rgb1_b.sin() + 1.0;
+        (r, g, b)
+    }
+
+    fn get_rgb_color2(&mut self, steps: f32) -> (f32, f32, f32) {
+        let k = self.params.color_speed * steps;
+        self.rgb2_r += 0.010 * k;
+        self.rgb2_g += 0.007 * k;
+        self.rgb2_b += 0.013 * k;
+        let r = self.rgb2_r.sin() + 1.0;
+        let g = self.rgb2_g.sin() + 1.0;
+        let b = self.rgb2_b.sin() + 1.0;
+        (r, g, b)
+    }
+
+    fn init_scene(&mut self) {
+        let mut setup = SceneSetup {
+            width: self.width,
+            height: self.height,
+            params: &self.params,
+            low_end: self.low_end_mode,
+            rng: &mut self.rng,
+        };
+        self.scene.init(&mut setup);
+        self.scene_ready = true;
+    }
+
+    fn seed_color_phases(&mut self) {
+        let mut phases = [0.0f32; 6];
+        for p in phases.iter_mut() {
+            *p = self.rng.next_f64() as f32 * PI2;
+        }
+        self.set_color_phases(phases);
+    }
+
+}
+
+#[allow(clippy::too_many_arguments)]
+fn upscale(filter: UpscaleFilter, src: &[u8], sw: usize, sh: usize, dst: &mut [u8], dw: usize, dh: usize, tile_rows: usize) {
+    tiles::bands(dst, dw * 4, tiles::band_rows(dh, tile_rows), |top, band| match filter {
+        UpscaleFilter::Nearest => upscale_nearest(src, sw, sh, band, dw, dh, top),
+        UpscaleFilter::Bilinear => upscale_bilinear(src, sw, sh, band, dw, dh, top),
+    });
+}
+
+fn limit_color(r: f32, g: f32, b: f32, min_val: f32) -> (f32, f32, f32) {
+    (r.max(min_val), g.max(min_val), b.max(min_val))
+}
+
@@ -0,0 +1,129 @@
+//! Visual modes the renderer can switch between. A scene owns its geometry and
+//! animation; the renderer owns audio analysis, colors, post-processing and the
+//! framebuffers, and applies them the same way whichever scene is active.
+
+use crate::palette::DepthGradient;
+use crate::params::TunnelParams;
+use crate::renderer::{FaceFill, LineCap, LineMode};
+use crate::rng::Rng;
+use crate::tunnel::Tunnel;
+
+/// Everything a scene needs to (re)build its state.
+pub struct SceneSetup<'a> {
+    /// Output size; scenes lay out in these coordinates at any render scale.
+    pub width: u32,
+    pub height: u32,
+    pub params: &'a TunnelParams,
+    pub low_end: bool,
+    /// Every random choice must come from here so a seed reproduces a session.
+    pub rng: &'a mut Rng,
+}
+
+/// Per-frame audio features, computed once by the renderer for any scene.
+pub struct AudioFeatures<'a> {
+    /// Latest magnitudes, 0-255 per bin in AnalyserNode layout.
+    pub spectrum: &'a [u8],
+    /// Mean level over all bins, 0..1.
+    pub energy: f32,
+    /// Mean level of the lowest bins, 0..1.
+    pub bass: f32,
+    /// Beat pulse, 0..6.5; follows the tracked tempo as `tempo_lock` rises.
+    pub beat: f32,
+    /// Decaying responses to the latest kick and hi-hat hits, 0..1.
+    pub kick: f32,
+    pub hat: f32,
+}
+
+/// Inputs for one frame.
+pub struct FrameInput<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub audio: AudioFeatures<'a>,
+    /// Seconds since the last rendered frame, including skipped ones.
+    pub dt: f32,
+    /// `dt` in 60 Hz reference frames, the unit the animation rates are tuned in.
+    pub steps: f32,
+    /// The fast and slow color oscillators, each channel 0..2.
+    pub color1: (f32, f32, f32),
+    pub color2: (f32, f32, f32),
+    /// Set in the depth palette mode: rows pick their colors by distance
+    /// instead; `color1`/`color2` then hold the nearest row's.
+    pub depth_gradient: Option<DepthGradient<'a>>,
+    /// Pointer in output pixels; `pointer_down` reverses and inverts.
+    pub pointer: (f32, f32),
+    pub pointer_active: bool,
+    pub pointer_down: bool,
+    /// Spectrum damping, higher is less reactive (see `Renderer::set_sensitivity`).
+    pub sensitivity: f32,
+    pub params: &'a TunnelParams,
+    pub low_end: bool,
+}
+
+/// Where a scene draws: an RGBA buffer at internal resolution, cleared to opaque black.
+pub struct DrawTarget<'a> {
+    pub pixels: &'a mut [u8],
+    /// Nearest z per pixel, reset to infinity, when the depth test is on.
+    pub depth: Option<&'a mut [f32]>,
+    pub width: usize,
+    pub height: usize,
+    /// Internal pixels per output pixel along each axis.
+    pub scale_x: f32,
+    pub scale_y: f32,
+    /// Rasterization settings, already forced to the cheap path in low-end mode.
+    pub line_mode: LineMode,
+    pub line_cap: LineCap,
+    pub face_fill: FaceFill,
+    /// Rows per band for scenes that draw bands in parallel (see `tiles`); the
+    /// whole height unless threads are enabled or the renderer was asked for bands.
+    pub band_rows: usize,
+}
+
+/// A visual mode. Each frame the renderer calls `update` then `draw`.
+pub trait Scene {
+    /// Rebuilds all state. Called when the output size, seed, low-end mode or
+    /// a geometry param changes.
+    fn init(&mut self, setup: &mut SceneSetup);
+
+    /// Advances the animation by `frame.dt`.
+    fn update(&mut self, frame: &FrameInput);
+
+    /// Draws the state left by the last `update`.
+    fn draw(&mut self, frame: &FrameInput, target: &mut DrawTarget);
+}
+
+/// Builds a scene in its initial state.
+pub type SceneFactory = fn() -> Box<dyn Scene>;
+
+/// Scenes by name, for switching at runtime. The default registry holds the
+/// built-in scenes, `"tunnel"` first.
+pub struct SceneRegistry {
+    entries: Vec<(&'static str, SceneFactory)>,
+}
+
+impl Default for SceneRegistry {
+    fn default() -> SceneRegistry {
+        SceneRegistry { entries: vec![("tunnel", || Box::new(Tunnel::default()))] }
+    }
+}
+
+impl SceneRegistry {
+    /// Adds a scene, or replaces the one registered under `name`.
+    pub fn register(&mut self, name: &'static str, factory: SceneFactory) {
+        match self.entries.iter_mut().find(|(n, _)| *n == name) {
+            Some(entry) => entry.1 = factory,
+            None => self.entries.push((name, factory)),
+        }
+    }
+
+    pub fn create(&self, name: &str) -> Result<Box<dyn Scene>, String> {
+        self.entries
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, factory)| factory())
+            .ok_or_else(|| format!("unknown scene {:?} (expected {})", name, self.names().join(", ")))
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.entries.iter().map(|(n, _)| *n).collect()
+    }
+}
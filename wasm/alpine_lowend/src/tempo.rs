@@ -0,0 +1,207 @@
+//! Tempo tracking from successive spectra: onset-strength envelope,
+//! autocorrelation tempo estimate with a comb over the first harmonic, and a
+//! beat phase that is pulled toward the onsets it predicts.
+
+// Onset envelope sample rate (Hz) and how much of it the estimate looks at
+const ENV_RATE: f32 = 100.0;
+const HISTORY: usize = 800;
+// Estimation needs this many samples and reruns every UPDATE_EVERY samples
+const MIN_HISTORY: usize = 400;
+const UPDATE_EVERY: usize = 25;
+const MIN_BPM: f32 = 60.0;
+const MAX_BPM: f32 = 200.0;
+// Log-Gaussian tempo prior: centre and width in octaves, to settle octave ambiguity
+const PRIOR_BPM: f32 = 120.0;
+const PRIOR_OCTAVES: f32 = 1.0;
+// Estimates within this fraction of the current tempo are blended rather than jumped to
+const BLEND_RANGE: f32 = 0.04;
+// Number of past beats the phase alignment sums over
+const PHASE_BEATS: usize = 4;
+
+pub struct TempoTracker {
+    prev: Vec<f32>,
+    // Ring buffer of onset strength at ENV_RATE
+    env: Vec<f32>,
+    env_pos: usize,
+    env_filled: usize,
+    slot_time: f32,
+    slot_onset: f32,
+    since_update: usize,
+    bpm: f32,
+    confidence: f32,
+    phase: f32,
+    scratch: Vec<f32>,
+    ac: Vec<f32>,
+}
+
+impl Default for TempoTracker {
+    fn default() -> TempoTracker {
+        TempoTracker {
+            prev: Vec::new(),
+            env: vec![0.0; HISTORY],
+            env_pos: 0,
+            env_filled: 0,
+            slot_time: 0.0,
+            slot_onset: 0.0,
+            since_update: 0,
+            bpm: 0.0,
+            confidence: 0.0,
+            phase: 0.0,
+            scratch: Vec::with_capacity(HISTORY),
+            ac: Vec::new(),
+        }
+    }
+}
+
+impl TempoTracker {
+    /// Estimated tempo in beats per minute, 0 until there is enough signal.
+    pub fn bpm(&self) -> f32 {
+        self.bpm
+    }
+
+    /// Position within the current beat: 0 on the beat, rising toward 1.
+    pub fn beat_phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// How periodic the recent onsets are, 0..1.
+    pub fn confidence(&self) -> f32 {
+        self.confidence
+    }
+
+    /// Onset strength of the latest spectrum, 0..1 (positive spectral flux).
+    pub fn onset(&self) -> f32 {
+        self.slot_onset
+    }
+
+    pub fn reset(&mut self) {
+        *self = TempoTracker::default();
+    }
+
+    /// Feeds one spectrum (0-255 per bin) that arrived `dt` seconds after the previous one.
+    pub fn push(&mut self, spectrum: &[u8], dt: f32) {
+        let dt = if dt.is_finite() { dt.clamp(0.0, 0.25) } else { 0.0 };
+        if self.prev.len() != spectrum.len() {
+            self.prev = spectrum.iter().map(|&v| v as f32).collect();
+        }
+        let mut flux = 0.0;
+        for (prev, &v) in self.prev.iter_mut().zip(spectrum) {
+            let v = v as f32;
+            flux += (v - *prev).max(0.0);
+            *prev = v;
+        }
+        let onset = flux / (spectrum.len().max(1) as f32 * 255.0);
+        self.slot_onset = self.slot_onset.max(onset);
+
+        if self.bpm > 0.0 {
+            self.phase = (self.phase + dt * self.bpm / 60.0).fract();
+        }
+
+        self.slot_time += dt;
+        let slot = 1.0 / ENV_RATE;
+        while self.slot_time >= slot {
+            self.slot_time -= slot;
+            self.env[self.env_pos] = self.slot_onset;
+            self.env_pos = (self.env_pos + 1) % HISTORY;
+            self.env_filled = (self.env_filled + 1).min(HISTORY);
+            self.slot_onset = 0.0;
+            self.since_update += 1;
+        }
+        if self.since_update >= UPDATE_EVERY && self.env_filled >= MIN_HISTORY {
+            self.since_update = 0;
+            self.estimate();
+        }
+    }
+
+    fn estimate(&mut self) {
+        let n = self.env_filled;
+        // Oldest first
+        self.scratch.clear();
+        for i in 0..n {
+            self.scratch.push(self.env[(self.env_pos + HISTORY - n + i) % HISTORY]);
+        }
+        // Spread each onset over neighbouring slots so frame-timing jitter
+        // does not split a peak between adjacent lags
+        let mut prev = 0.0;
+        for i in 0..n {
+            let cur = self.scratch[i];
+            let next = if i + 1 < n { self.scratch[i + 1] } else { 0.0 };
+            self.scratch[i] = 0.25 * prev + 0.5 * cur + 0.25 * next;
+            prev = cur;
+        }
+        let mean = self.scratch.iter().sum::<f32>() / n as f32;
+        for v in self.scratch.iter_mut() {
+            *v -= mean;
+        }
+        let x = &self.scratch;
+        let energy: f32 = x.iter().map(|v| v * v).sum();
+        if energy <= 1e-9 {
+            self.bpm = 0.0;
+            self.confidence = 0.0;
+            return;
+        }
+
+        let min_lag = (60.0 * ENV_RATE / MAX_BPM).floor() as usize;
+        let max_lag = (60.0 * ENV_RATE / MIN_BPM).ceil() as usize;
+        let ac_len = (max_lag * 2 + 2).min(n - 1);
+        self.ac.clear();
+        self.ac.push(1.0);
+        for lag in 1..ac_len {
+            let sum: f32 = x[lag..].iter().zip(x).map(|(a, b)| a * b).sum();
+            // Unbiased: compensate for the shrinking overlap
+            self.ac.push(sum * n as f32 / (n - lag) as f32 / energy);
+        }
+        let ac = &self.ac;
+        let comb = |lag: usize| ac[lag] + ac.get(lag * 2).map_or(0.0, |v| v * 0.5);
+        let prior = |lag: f32| {
+            let octaves = (60.0 * ENV_RATE / lag / PRIOR_BPM).log2() / PRIOR_OCTAVES;
+            (-0.5 * octaves * octaves).exp()
+        };
+
+        let top = max_lag.min(ac_len - 2);
+        let Some(best) = (min_lag..=top).max_by(|&a, &b| {
+            (comb(a) * prior(a as f32)).total_cmp(&(comb(b) * prior(b as f32)))
+        }) else {
+            return;
+        };
+        // Parabolic interpolation between neighbouring lags
+        let (l, c, r) = (comb(best - 1), comb(best), comb(best + 1));
+        let denom = l - 2.0 * c + r;
+        let offset = if denom < 0.0 { (0.5 * (l - r) / denom).clamp(-0.5, 0.5) } else { 0.0 };
+        let lag = best as f32 + offset;
+
+        let confidence = ac[best].clamp(0.0, 1.0);
+        let bpm = 60.0 * ENV_RATE / lag;
+        let close = self.bpm > 0.0 && (bpm - self.bpm).abs() <= self.bpm * BLEND_RANGE;
+        self.bpm = if close { self.bpm * 0.8 + bpm * 0.2 } else { bpm };
+        self.confidence = confidence;
+        self.align_phase(!close);
+    }
+
+    // Finds where the last beats fell in the raw envelope and steers the phase toward it
+    fn align_phase(&mut self, snap: bool) {
+        let period = 60.0 * ENV_RATE / self.bpm;
+        let n = self.env_filled;
+        let at = |ago: usize| -> f32 {
+            if ago >= n {
+                return 0.0;
+            }
+            self.env[(self.env_pos + HISTORY - 1 - ago) % HISTORY]
+        };
+        let Some(offset) = (0..period.ceil() as usize).max_by(|&a, &b| {
+            let score = |o: usize| (0..PHASE_BEATS).map(|k| at(o + (k as f32 * period).round() as usize)).sum::<f32>();
+            score(a).total_cmp(&score(b))
+        }) else {
+            return;
+        };
+        // `offset` slots ago was a beat, plus the part of the current slot already elapsed
+        let target = ((offset as f32 + 0.5 + self.slot_time * ENV_RATE) / period).fract();
+        if snap {
+            self.phase = target;
+        } else {
+            let mut error = target - self.phase;
+            error -= error.round();
+            self.phase = (self.phase + error * 0.5).rem_euclid(1.0);
+        }
+    }
+}
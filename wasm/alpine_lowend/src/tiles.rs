@@ -0,0 +1,72 @@
+//! Splits per-frame work into horizontal bands of rows. With the `threads`
+//! feature native builds run the bands in parallel on rayon's global pool.
+//! Otherwise, and always in wasm, they run in order on the calling thread, and
+//! by default the whole frame is one band. The browser renders on its main
+//! thread, which may not block waiting for rayon's workers.
+
+/// Smallest band picked automatically; thinner ones cost more in setup than they save.
+const MIN_BAND_ROWS: usize = 16;
+// Bands per worker, so uneven bands still keep every thread busy
+const BANDS_PER_THREAD: usize = 4;
+
+/// Worker threads available for bands; 1 in the single-threaded build.
+pub fn threads() -> usize {
+    #[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+    {
+        rayon::current_num_threads()
+    }
+    #[cfg(not(all(feature = "threads", not(target_arch = "wasm32"))))]
+    {
+        1
+    }
+}
+
+/// Sizes the worker pool; call before the first frame, since rayon's global
+/// pool starts once. Without the `threads` feature, or in wasm, only 1 is accepted.
+pub fn init_threads(count: usize) -> Result<(), String> {
+    #[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+    {
+        rayon::ThreadPoolBuilder::new().num_threads(count).build_global().map_err(|e| e.to_string())
+    }
+    #[cfg(not(all(feature = "threads", not(target_arch = "wasm32"))))]
+    {
+        match count {
+            1 => Ok(()),
+            _ => Err(format!("cannot use {} threads: built without the threads feature", count)),
+        }
+    }
+}
+
+/// Rows per band for a frame `height` rows tall: `requested` if non-zero,
+/// otherwise enough bands to share out among the worker threads.
+pub fn band_rows(height: usize, requested: usize) -> usize {
+    let rows = match (requested, threads()) {
+        (0, 1) => height,
+        (0, n) => height.div_ceil(n * BANDS_PER_THREAD).max(MIN_BAND_ROWS),
+        (rows, _) => rows,
+    };
+    rows.max(1)
+}
+
+/// Calls `f` on every item, in parallel in native `threads` builds.
+pub(crate) fn for_each<T: Send>(items: Vec<T>, f: impl Fn(T) + Sync + Send) {
+    #[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+        if items.len() > 1 {
+            items.into_par_iter().for_each(f);
+            return;
+        }
+    }
+    items.into_iter().for_each(f);
+}
+
+/// Calls `f(first_row, band)` on consecutive bands of `rows` rows of `buf`,
+/// which holds `row_len` elements per row.
+pub(crate) fn bands<T: Send>(buf: &mut [T], row_len: usize, rows: usize, f: impl Fn(usize, &mut [T]) + Sync + Send) {
+    if row_len == 0 {
+        return;
+    }
+    let items: Vec<_> = buf.chunks_mut(row_len * rows).enumerate().map(|(i, band)| (i * rows, band)).collect();
+    for_each(items, |(top, band)| f(top, band));
+}
@@ -0,0 +1,154 @@
+//! Drum-hit detection on three spectrum bands. Each band thresholds its
+//! positive flux against its own running mean and deviation, then stays quiet
+//! for a refractory period after firing.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrumKind {
+    Kick,
+    Snare,
+    Hat,
+}
+
+impl DrumKind {
+    pub const ALL: [DrumKind; 3] = [DrumKind::Kick, DrumKind::Snare, DrumKind::Hat];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DrumKind::Kick => "kick",
+            DrumKind::Snare => "snare",
+            DrumKind::Hat => "hat",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransientEvent {
+    pub kind: DrumKind,
+    /// 0..1; 1 means the flux was at least twice the band's threshold.
+    pub strength: f32,
+    /// Seconds since the detector started.
+    pub time: f32,
+}
+
+// Band edges in Hz and the minimum gap between hits
+struct BandSpec {
+    kind: DrumKind,
+    low_hz: f32,
+    high_hz: f32,
+    refractory: f32,
+}
+
+const BANDS: [BandSpec; 3] = [
+    BandSpec { kind: DrumKind::Kick, low_hz: 0.0, high_hz: 200.0, refractory: 0.12 },
+    BandSpec { kind: DrumKind::Snare, low_hz: 200.0, high_hz: 4000.0, refractory: 0.10 },
+    BandSpec { kind: DrumKind::Hat, low_hz: 6000.0, high_hz: 16000.0, refractory: 0.05 },
+];
+
+// Threshold = mean + SENSITIVITY * deviation + FLOOR, stats averaged over STATS_TAU seconds
+const SENSITIVITY: f32 = 2.5;
+const FLOOR: f32 = 0.01;
+const STATS_TAU: f32 = 1.0;
+// Undrained events beyond this are dropped, oldest first
+const MAX_PENDING: usize = 64;
+
+#[derive(Clone, Copy, Default)]
+struct BandState {
+    prev: f32,
+    mean: f32,
+    dev: f32,
+    since_hit: f32,
+}
+
+pub struct TransientDetector {
+    nyquist_hz: f32,
+    time: f32,
+    bands: [BandState; 3],
+    // False until the first spectrum has set each band's starting level
+    primed: bool,
+    events: Vec<TransientEvent>,
+    pending: Vec<TransientEvent>,
+}
+
+impl Default for TransientDetector {
+    fn default() -> TransientDetector {
+        TransientDetector {
+            nyquist_hz: 24000.0,
+            time: 0.0,
+            bands: [BandState { since_hit: f32::INFINITY, ..BandState::default() }; 3],
+            primed: false,
+            events: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl TransientDetector {
+    /// Frequency of the last spectrum bin (half the sample rate); defaults to 24 kHz.
+    pub fn set_nyquist_hz(&mut self, hz: f32) {
+        if hz.is_finite() && hz > 0.0 {
+            self.nyquist_hz = hz;
+        }
+    }
+
+    pub fn nyquist_hz(&self) -> f32 {
+        self.nyquist_hz
+    }
+
+    /// Feeds one spectrum (0-255 per bin) that arrived `dt` seconds after the
+    /// previous one; returns the hits it triggered.
+    pub fn push(&mut self, spectrum: &[u8], dt: f32) -> &[TransientEvent] {
+        let dt = if dt.is_finite() { dt.clamp(0.0, 0.25) } else { 0.0 };
+        self.time += dt;
+        self.events.clear();
+        if spectrum.is_empty() {
+            return &self.events;
+        }
+        let rate = 1.0 - (-dt / STATS_TAU).exp();
+        for (spec, state) in BANDS.iter().zip(self.bands.iter_mut()) {
+            let level = band_level(spectrum, self.nyquist_hz, spec);
+            if !self.primed {
+                state.prev = level;
+            }
+            let flux = (level - state.prev).max(0.0);
+            state.prev = level;
+            state.since_hit += dt;
+
+            let threshold = state.mean + SENSITIVITY * state.dev + FLOOR;
+            if flux > threshold && state.since_hit >= spec.refractory {
+                state.since_hit = 0.0;
+                let strength = (flux / threshold - 1.0).clamp(0.0, 1.0);
+                self.events.push(TransientEvent { kind: spec.kind, strength, time: self.time });
+            }
+            state.mean += (flux - state.mean) * rate;
+            state.dev += ((flux - state.mean).abs() - state.dev) * rate;
+        }
+        self.primed = true;
+        self.pending.extend_from_slice(&self.events);
+        if self.pending.len() > MAX_PENDING {
+            let excess = self.pending.len() - MAX_PENDING;
+            self.pending.drain(..excess);
+        }
+        &self.events
+    }
+
+    /// Hits from the most recent `push`.
+    pub fn events(&self) -> &[TransientEvent] {
+        &self.events
+    }
+
+    /// Hits since the last drain, oldest first.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, TransientEvent> {
+        self.pending.drain(..)
+    }
+}
+
+// Mean level of the bins inside the band, 0..1; at least one bin wide
+fn band_level(spectrum: &[u8], nyquist_hz: f32, spec: &BandSpec) -> f32 {
+    let bins = spectrum.len();
+    let bin = |hz: f32| ((hz / nyquist_hz * bins as f32) as usize).min(bins - 1);
+    let start = bin(spec.low_hz);
+    let end = bin(spec.high_hz).max(start + 1).min(bins);
+    let start = start.min(end - 1);
+    let sum: u32 = spectrum[start..end].iter().map(|&v| v as u32).sum();
+    sum as f32 / ((end - start) as f32 * 255.0)
+}
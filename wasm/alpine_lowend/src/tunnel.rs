@@ -0,0 +1,491 @@
+//! The original scene: rings of audio-displaced segments flying toward the viewer.
+
+use crate::raster::{draw_line, draw_line_aa, draw_wide_line, fill_convex, Blend, Canvas};
+use crate::renderer::{FaceFill, LineMode};
+use crate::scene::{DrawTarget, FrameInput, Scene, SceneSetup};
+use crate::tiles;
+
+#[derive(Clone)]
+struct Segment {
+    x: f32,
+    y: f32,
+    x2d: f32,
+    y2d: f32,
+    index: i32,
+    radius: f32,
+    radius_audio: f32,
+    segments: i32,
+    audio_buffer_index: usize,
+    subs: Vec<Segment>,
+}
+
+struct CircleRow {
+    segments_outside: Vec<Option<Segment>>,
+    z: f32,
+    center_x: f32,
+    center_y: f32,
+    circle_center_x: f32,
+    circle_center_y: f32,
+    mp_x: f32,
+    mp_y: f32,
+    radius: f32,
+    color_r: f32,
+    color_g: f32,
+    color_b: f32,
+}
+
+// One segment as it is drawn this frame; corners are in output coordinates
+struct Cell {
+    // p1..p8: inner face (p1-p4) and tube wall (p5-p8), this row then the one behind
+    corners: [(f32, f32); 8],
+    z: f32,
+    back_z: f32,
+    color: (u8, u8, u8),
+    frequency: f32,
+    // Audio pushed the inner face out, so it and its sides are drawn
+    displaced: bool,
+    // Close enough for the wall outline
+    outline: bool,
+}
+
+const PI2: f32 = std::f32::consts::PI * 2.0;
+
+// How far lines are pulled toward the camera in the depth test, in z units
+const LINE_DEPTH_BIAS: f32 = 1.0;
+
+// Face brightness by orientation, for flat shading
+const WALL_SHADE: f32 = 0.55;
+const SIDE_SHADE: f32 = 0.8;
+const INNER_SHADE: f32 = 1.0;
+
+#[derive(Default)]
+pub(crate) struct Tunnel {
+    circles: Vec<CircleRow>,
+    cos_table: Vec<f32>,
+    sin_table: Vec<f32>,
+    time: f32,
+    // Eased offset of the perspective center from its rest position
+    steer_x: f32,
+    steer_y: f32,
+    // Built by `update`, rasterized by `draw`
+    cells: Vec<Cell>,
+}
+
+impl Scene for Tunnel {
+    // Rebuilds trig tables and rows for the current segment count and spacing
+    fn init(&mut self, setup: &mut SceneSetup) {
+        let p = setup.params;
+        let segments = if setup.low_end { p.low_end_segments } else { p.segments };
+        self.cos_table.clear();
+        self.sin_table.clear();
+        for i in 0..segments {
+            let ang = i as f32 * (PI2 / segments as f32);
+            self.cos_table.push(ang.cos());
+            self.sin_table.push(ang.sin());
+        }
+        let step = if setup.low_end { p.low_end_ring_step } else { p.ring_step };
+        self.circles = self.build_circles(setup, segments, step);
+        self.cells.clear();
+    }
+
+    fn update(&mut self, frame: &FrameInput) {
+        let p = frame.params;
+        let steps = frame.steps;
+        let (w, h) = (frame.width as f32, frame.height as f32);
+        let fov = p.fov;
+        let fog_near_z = fov * p.fog_near;
+        let fog_far_z = fov * p.fog_far;
+        let low_end_mode = frame.low_end;
+        let frequency_damp = frame.sensitivity;
+        let energy_avg = frame.audio.energy;
+        let beat_boost = frame.audio.beat;
+        let kick_speed = frame.audio.kick * p.kick_speed;
+        let hat_flash = frame.audio.hat * p.hat_sparkle;
+        let depth_gradient = frame.depth_gradient;
+
+        // Ease the perspective center toward the pointer, or back to rest once it leaves
+        let rest_x = (w / 2.0) + p.perspective_offset_x;
+        let rest_y = (h / 2.0) + p.perspective_offset_y;
+        let (target_x, target_y) = if frame.pointer_active {
+            ((frame.pointer.0 - rest_x) * p.mouse_steer, (frame.pointer.1 - rest_y) * p.mouse_steer)
+        } else {
+            (0.0, 0.0)
+        };
+        let ease = 1.0 - p.steer_inertia.powf(steps);
+        self.steer_x += (target_x - self.steer_x) * ease;
+        self.steer_y += (target_y - self.steer_y) * ease;
+        let (steer_x, steer_y) = (self.steer_x, self.steer_y);
+
+        let mut sort_needed = false;
+
+        let vu_data = frame.audio.spectrum;
+        let cos_sin_table = (&self.cos_table, &self.sin_table);
+        let circles = &mut self.circles;
+        let cells = &mut self.cells;
+        cells.clear();
+        let total = circles.len();
+
+        for i in 0..total {
+            let (head, tail) = circles.split_at_mut(i);
+            let circle = &mut tail[0];
+            let prev_opt = if i > 0 { Some(&head[i - 1]) } else { None };
+
+            // Update colors
+            let ((col_r, col_g, col_b), (col2_r, col2_g, col2_b)) = match &depth_gradient {
+                Some(g) => g.colors((circle.z + fov) / (fov * 2.0)),
+                None => (frame.color1, frame.color2),
+            };
+            circle.color_r = col_r - (circle.z + fov) / fov;
+            circle.color_g = col_g - (circle.z + fov) / fov;
+            circle.color_b = col_b - (circle.z + fov) / fov;
+
+            circle.color_r = circle.color_r.max(col2_r);
+            circle.color_g = circle.color_g.max(col2_g);
+            circle.color_b = circle.color_b.max(col2_b);
+
+            circle.mp_x = (w / 2.0) + p.perspective_offset_x + steer_x;
+            circle.mp_y = (h / 2.0) + p.perspective_offset_y + steer_y;
+
+            // Calculate center with perspective
+            circle.center_x = ((w / 2.0) - circle.mp_x) * ((circle.z - fov) / p.perspective_depth_divisor) + w / 2.0;
+            circle.center_y = ((h / 2.0) - circle.mp_y) * ((circle.z - fov) / p.perspective_depth_divisor) + h / 2.0;
+
+            let scale = fov / (fov + circle.z);
+            let scale_back = prev_opt.map(|p| fov / (fov + p.z)).unwrap_or(scale);
+
+            // Frustum culling: skip rows fully outside viewport
+            let min_x = (circle.center_x - circle.radius).floor() as i32;
+            let max_x = (circle.center_x + circle.radius).ceil() as i32;
+            let min_y = (circle.center_y - circle.radius).floor() as i32;
+            let max_y = (circle.center_y + circle.radius).ceil() as i32;
+            let w_i32 = frame.width as i32;
+            let h_i32 = frame.height as i32;
+            let offscreen = max_x < 0 || min_x >= w_i32 || max_y < 0 || min_y >= h_i32;
+            if offscreen { continue; }
+
+            // Process segments
+            for seg in circle.segments_outside.iter_mut().flatten() {
+                seg.x2d = seg.x * scale + circle.center_x;
+                seg.y2d = seg.y * scale + circle.center_y;
+
+                let frequency = vu_data[seg.audio_buffer_index % vu_data.len()] as f32;
+                let frequency_add = frequency / frequency_damp * (1.0 + beat_boost*0.3);
+
+                seg.radius_audio = seg.radius - frequency_add;
+
+                // Skip complex geometry on distant rows for performance
+                if low_end_mode && circle.z.abs() > fov * 0.8 {
+                    continue;
+                }
+
+                // Build 3D faces
+                if i > 0 && i < total - 1 && seg.subs.len() >= 7 {
+                    let brightness_base = 20.0 + energy_avg * 200.0;
+                    let line_value = ((i as f32 / total as f32) * (brightness_base + frequency)).min(255.0);
+
+                    // Depth fog per row (cheap)
+                    // Fog only for far side (positive z). Near (negative z) remains clear.
+                    let mut fog = if circle.z <= fog_near_z { 0.0 } else { ((circle.z - fog_near_z) / (fog_far_z - fog_near_z)).clamp(0.0, 1.0) };
+                    // smoothstep
+                    fog = fog * fog * (3.0 - 2.0 * fog);
+                    if fog >= 0.999 { continue; }
+
+                    // Increase saturation by pushing away from gray
+                    let mut crf = circle.color_r * line_value;
+                    let mut cgf = circle.color_g * line_value;
+                    let mut cbf = circle.color_b * line_value;
+                    let luma = (crf + cgf + cbf) / 3.0;
+                    crf = (luma + (crf - luma) * p.saturation_boost).min(255.0);
+                    cgf = (luma + (cgf - luma) * p.saturation_boost).min(255.0);
+                    cbf = (luma + (cbf - luma) * p.saturation_boost).min(255.0);
+                    // Apply fog (mix toward fog brightness)
+                    let fog_mix = fog;
+                    crf = crf * (1.0 - fog_mix) + (p.fog_brightness * 255.0) * fog_mix;
+                    cgf = cgf * (1.0 - fog_mix) + (p.fog_brightness * 255.0) * fog_mix;
+                    cbf = cbf * (1.0 - fog_mix) + (p.fog_brightness * 255.0) * fog_mix;
+
+                    let white_mix = ((beat_boost / 6.5).min(5.0)).powf(1.8) * 0.6 + hat_flash;
+                    if white_mix > 0.0 {
+                        crf = crf + (255.0 - crf) * white_mix;
+                        cgf = cgf + (255.0 - cgf) * white_mix;
+                        cbf = cbf + (255.0 - cbf) * white_mix;
+                    }
+
+                    // Use lookup tables for trig functions
+                    let seg_count = seg.segments as usize;
+
+                    // Helper to get trig values from lookup table
+                    let get_trig = |index: i32| -> (f32, f32) {
+                        let idx = (index as usize) % seg_count;
+                        (cos_sin_table.0[idx], cos_sin_table.1[idx])
+                    };
+
+                    // sub1 (index 0) - current row, audio radius
+                    let (cos_val, sin_val) = get_trig(seg.subs[0].index);
+                    seg.subs[0].x = circle.circle_center_x + cos_val * seg.radius_audio;
+                    seg.subs[0].y = circle.circle_center_y + sin_val * seg.radius_audio;
+                    seg.subs[0].x2d = seg.subs[0].x * scale + circle.center_x;
+                    seg.subs[0].y2d = seg.subs[0].y * scale + circle.center_y;
+
+                    // sub2 (index 1) - back row, audio radius
+                    let (cos_val, sin_val) = get_trig(seg.subs[1].index);
+                    seg.subs[1].x = circle.circle_center_x + cos_val * seg.radius_audio;
+                    seg.subs[1].y = circle.circle_center_y + sin_val * seg.radius_audio;
+                    if let Some(prev) = prev_opt {
+                        seg.subs[1].x2d = seg.subs[1].x * scale_back + prev.center_x;
+                        seg.subs[1].y2d = seg.subs[1].y * scale_back + prev.center_y;
+                    }
+
+                    // sub3 (index 2) - back row, audio radius
+                    let (cos_val, sin_val) = get_trig(seg.subs[2].index);
+                    seg.subs[2].x = circle.circle_center_x + cos_val * seg.radius_audio;
+                    seg.subs[2].y = circle.circle_center_y + sin_val * seg.radius_audio;
+                    if let Some(prev) = prev_opt {
+                        seg.subs[2].x2d = seg.subs[2].x * scale_back + prev.center_x;
+                        seg.subs[2].y2d = seg.subs[2].y * scale_back + prev.center_y;
+                    }
+
+                    // sub4 (index 3) - current row, normal radius
+                    let (cos_val, sin_val) = get_trig(seg.subs[3].index);
+                    seg.subs[3].x = circle.circle_center_x + cos_val * seg.radius;
+                    seg.subs[3].y = circle.circle_center_y + sin_val * seg.radius;
+                    seg.subs[3].x2d = seg.subs[3].x * scale + circle.center_x;
+                    seg.subs[3].y2d = seg.subs[3].y * scale + circle.center_y;
+
+                    // sub5 (index 4) - current row, normal radius
+                    let (cos_val, sin_val) = get_trig(seg.subs[4].index);
+                    seg.subs[4].x = circle.circle_center_x + cos_val * seg.radius;
+                    seg.subs[4].y = circle.circle_center_y + sin_val * seg.radius;
+                    seg.subs[4].x2d = seg.subs[4].x * scale + circle.center_x;
+                    seg.subs[4].y2d = seg.subs[4].y * scale + circle.center_y;
+
+                    // sub6 (index 5) - back row, normal radius
+                    let (cos_val, sin_val) = get_trig(seg.subs[5].index);
+                    seg.subs[5].x = circle.circle_center_x + cos_val * seg.radius;
+                    seg.subs[5].y = circle.circle_center_y + sin_val * seg.radius;
+                    if let Some(prev) = prev_opt {
+                        seg.subs[5].x2d = seg.subs[5].x * scale_back + prev.center_x;
+                        seg.subs[5].y2d = seg.subs[5].y * scale_back + prev.center_y;
+                    }
+
+                    // sub7 (index 6) - back row, normal radius
+                    let (cos_val, sin_val) = get_trig(seg.subs[6].index);
+                    seg.subs[6].x = circle.circle_center_x + cos_val * seg.radius;
+                    seg.subs[6].y = circle.circle_center_y + sin_val * seg.radius;
+                    if let Some(prev) = prev_opt {
+                        seg.subs[6].x2d = seg.subs[6].x * scale_back + prev.center_x;
+                        seg.subs[6].y2d = seg.subs[6].y * scale_back + prev.center_y;
+                    }
+
+                    let corner = |s: &Segment| (s.x2d, s.y2d);
+                    cells.push(Cell {
+                        corners: [
+                            corner(seg),
+                            corner(&seg.subs[1]),
+                            corner(&seg.subs[2]),
+                            corner(&seg.subs[0]),
+                            corner(&seg.subs[3]),
+                            corner(&seg.subs[4]),
+                            corner(&seg.subs[6]),
+                            corner(&seg.subs[5]),
+                        ],
+                        // Corners carry their row's z: this row, or the one behind it
+                        z: circle.z,
+                        back_z: prev_opt.map_or(circle.z, |p| p.z),
+                        color: (crf as u8, cgf as u8, cbf as u8),
+                        frequency,
+                        displaced: frequency_add > 0.0,
+                        outline: circle.z < fov / 3.0 && !low_end_mode,
+                    });
+                }
+
+                // Update segment position using lookup tables
+                let idx = (seg.index as usize) % (seg.segments as usize);
+                seg.x = circle.circle_center_x + cos_sin_table.0[idx] * seg.radius_audio;
+                seg.y = circle.circle_center_y + cos_sin_table.1[idx] * seg.radius_audio;
+            }
+
+            // Update Z position with beat-reactive speed
+            let bpm_wave = (beat_boost * 0.3).min(2.0);
+            let dynamic_speed = (p.speed + beat_boost + bpm_wave + kick_speed) * steps;
+            if frame.pointer_down {
+                circle.z += dynamic_speed;
+                if circle.z > fov {
+                    circle.z -= fov * 2.0;
+                    sort_needed = true;
+                }
+            } else {
+                circle.z -= dynamic_speed;
+                if circle.z < -fov {
+                    circle.z += fov * 2.0;
+                    sort_needed = true;
+                }
+            }
+        }
+
+        // Sort by depth if needed
+        if sort_needed {
+            circles.sort_by(|a, b| b.z.partial_cmp(&a.z).unwrap());
+        }
+
+        // Update time
+        if frame.pointer_down {
+            self.time -= 0.005 * steps;
+        } else {
+            self.time += 0.005 * steps;
+        }
+    }
+
+    fn draw(&mut self, frame: &FrameInput, target: &mut DrawTarget) {
+        let p = frame.params;
+        let fov = p.fov;
+        let (iw, ih) = (target.width, target.height);
+        let (sx, sy) = (target.scale_x, target.scale_y);
+        // Line width in internal pixels, never thinner than one
+        let thickness = p.mesh_thickness * sx;
+        let line_cap = target.line_cap;
+        let face_fill = target.face_fill;
+        let blend = match target.line_mode {
+            LineMode::Aliased => None,
+            LineMode::SmoothAdd => Some(Blend::Add),
+            LineMode::SmoothMax => Some(Blend::Max),
+        };
+        let cells = &self.cells;
+        let bands = Canvas::bands(target.pixels, target.depth.as_deref_mut(), iw, ih, target.band_rows);
+
+        // Every band walks all cells in order and keeps only its own rows
+        tiles::for_each(bands, |mut canvas| {
+            let c = &mut canvas;
+            for cell in cells {
+                let (cr, cg, cb) = cell.color;
+                let point = |k: usize, z: f32| (cell.corners[k].0 * sx, cell.corners[k].1 * sy, z);
+                let (z, back_z) = (cell.z, cell.back_z);
+                let [p1, p2, p3, p4] = [point(0, z), point(1, back_z), point(2, back_z), point(3, z)];
+                let [p5, p6, p7, p8] = [point(4, z), point(5, z), point(6, back_z), point(7, back_z)];
+
+                // Back to front: tube wall, sides, then the audio-displaced inner face
+                if face_fill != FaceFill::None {
+                    let fade = if face_fill == FaceFill::Depth { ((fov - cell.z) / (2.0 * fov)).clamp(0.1, 1.0) } else { 1.0 };
+                    let shade = |k: f32| {
+                        let k = k * fade * p.face_brightness;
+                        ((cr as f32 * k).min(255.0) as u8, (cg as f32 * k).min(255.0) as u8, (cb as f32 * k).min(255.0) as u8)
+                    };
+                    let mut fill = |pts: &[(f32, f32, f32)], k: f32| {
+                        let (r, g, b) = shade(k);
+                        fill_convex(c, pts, r, g, b, p.face_alpha);
+                    };
+                    fill(&[p5, p6, p7, p8], WALL_SHADE);
+                    if cell.displaced {
+                        fill(&[p1, p2, p8, p5], SIDE_SHADE);
+                        fill(&[p2, p3, p7, p8], SIDE_SHADE);
+                        fill(&[p3, p4, p6, p7], SIDE_SHADE);
+                        fill(&[p4, p1, p5, p6], SIDE_SHADE);
+                        fill(&[p1, p2, p3, p4], INNER_SHADE);
+                    }
+                }
+
+                let width = (thickness + p.thickness_audio * sx * cell.frequency / 255.0).max(1.0);
+                let mut line = |a: (f32, f32, f32), b: (f32, f32, f32)| {
+                    // Pulled slightly forward so edges win against their own faces
+                    let (za, zb) = (a.2 - LINE_DEPTH_BIAS, b.2 - LINE_DEPTH_BIAS);
+                    match blend {
+                        _ if width > 1.0 => draw_wide_line(c, a.0, a.1, za, b.0, b.1, zb, width, line_cap, cr, cg, cb, blend),
+                        None => draw_line(c, a.0 as i32, a.1 as i32, za, b.0 as i32, b.1 as i32, zb, cr, cg, cb),
+                        Some(blend) => draw_line_aa(c, a.0, a.1, za, b.0, b.1, zb, cr, cg, cb, blend),
+                    }
+                };
+
+                // Draw inner face only if there's audio deformation
+                if cell.displaced {
+                    line(p1, p2);
+                    line(p2, p3);
+                    line(p3, p4);
+                    line(p4, p1);
+
+                    // Connecting edges
+                    line(p5, p1);
+                    line(p6, p4);
+                    line(p7, p3);
+                    line(p8, p2);
+                }
+
+                // Outer face (if close enough)
+                if cell.outline {
+                    line(p5, p6);
+                    line(p6, p7);
+                    line(p7, p8);
+                    line(p8, p5);
+                }
+            }
+        });
+    }
+}
+
+impl Tunnel {
+    fn build_circles(&self, setup: &mut SceneSetup, segments: i32, step: i32) -> Vec<CircleRow> {
+        let rng = &mut *setup.rng;
+        let mut rows = Vec::new();
+        let audio_min = 8usize;
+        let audio_max = 1024usize;
+        let mp_x = rng.next_f64() as f32 * setup.width as f32;
+        let mp_y = rng.next_f64() as f32 * setup.height as f32;
+        let fov = setup.params.fov as i32;
+        for (index, z) in (-fov..fov).step_by(step as usize).enumerate() {
+            let radius = setup.params.ring_radius;
+            let mut segments_outside = Vec::new();
+            let mut coords = Vec::new();
+            for i in 0..=segments {
+                let angle = (i as f32) * (PI2 / segments as f32) + self.time;
+                let x = angle.cos() * radius;
+                let y = angle.sin() * radius;
+                coords.push((x, y, i));
+            }
+            let toggle = index % 2;
+            for i in 0..coords.len() {
+                if i % 2 == toggle {
+                    let audio_idx = audio_min + ((rng.next_f64() * ((audio_max - audio_min) as f64)) as usize);
+                    let (x, y, idx) = coords[i];
+                    let (prev_x, prev_y, prev_idx) = if i > 0 {
+                        coords[i - 1]
+                    } else {
+                        coords[coords.len() - 2]
+                    };
+                    let sub = |x: f32, y: f32, index: i32| Segment {
+                        x, y, x2d: 0.0, y2d: 0.0,
+                        index, radius, radius_audio: radius,
+                        segments, audio_buffer_index: audio_idx,
+                        subs: vec![]
+                    };
+                    let subs = vec![
+                        sub(prev_x, prev_y, prev_idx),
+                        sub(x, y, idx),
+                        sub(prev_x, prev_y, prev_idx),
+                        sub(x, y, idx),
+                        sub(prev_x, prev_y, prev_idx),
+                        sub(x, y, idx),
+                        sub(prev_x, prev_y, prev_idx),
+                    ];
+                    let segment = Segment { subs, ..sub(x, y, idx) };
+                    segments_outside.push(Some(segment));
+                } else {
+                    segments_outside.push(None);
+                }
+            }
+            rows.push(CircleRow {
+                segments_outside,
+                z: z as f32,
+                center_x: 0.0,
+                center_y: 0.0,
+                circle_center_x: 0.0,
+                circle_center_y: 0.0,
+                mp_x,
+                mp_y,
+                radius,
+                color_r: 0.0,
+                color_g: 0.0,
+                color_b: 0.0,
+            });
+        }
+        rows
+    }
+}
@@ -0,0 +1,1011 @@
+//! wasm-bindgen front end: exposes `Visualizer` to JS and drives the DOM VU meters.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document, HtmlElement};
+
+use crate::analysis::{AnalysisConfig, Analyser, Window};
+use crate::governor::Governor;
+use crate::params::PARAM_SPECS;
+use crate::post::PostKind;
+use crate::palette::{Palette, PaletteMode, PALETTE_NAMES};
+use crate::preset::{Preset, PresetBank};
+use crate::renderer::{FaceFill, LineCap, LineMode, Renderer, UpscaleFilter, REFERENCE_HZ};
+use crate::transient::DrumKind;
+
+// DOM handles for the 24-column VU meter under #vuBars
+struct VuBars {
+    segs: Vec<HtmlElement>,
+    cuts: Vec<HtmlElement>,
+    lows: Vec<HtmlElement>,
+    mids: Vec<HtmlElement>,
+    highs: Vec<HtmlElement>,
+    prev_low: Vec<f32>,
+    prev_mid: Vec<f32>,
+    prev_high: Vec<f32>,
+}
+
+/// One independent tunnel: owns its framebuffers, audio state and color phases.
+/// Several can run side by side on one page (e.g. a main view and a preview).
+#[wasm_bindgen]
+pub struct Visualizer {
+    renderer: Renderer,
+    analysis_config: AnalysisConfig,
+    // Built on the first `update_pcm` and rebuilt when the sample rate changes
+    analyser: Option<Analyser>,
+    vu_bars: Option<VuBars>,
+    mini_vu_blocks: Option<Vec<HtmlElement>>,
+    presets: PresetBank,
+    preset_buttons: Option<Vec<HtmlElement>>,
+    // Adaptive quality; times its own render_frame calls until the host reports timings
+    governor: Option<Governor>,
+    host_timing: bool,
+    // performance.now() of the previous render_frame, for the frame interval
+    last_frame_ms: Option<f64>,
+}
+
+#[wasm_bindgen]
+impl Visualizer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u32, height: u32) -> Visualizer {
+        let mut renderer = Renderer::new(width, height);
+        // Each session gets its own seed; set_seed makes a run reproducible
+        renderer.set_seed((js_sys::Math::random() * (1u64 << 53) as f64) as u64);
+        Visualizer {
+            renderer,
+            analysis_config: AnalysisConfig::default(),
+            analyser: None,
+            vu_bars: None,
+            mini_vu_blocks: None,
+            presets: PresetBank::default(),
+            preset_buttons: None,
+            governor: None,
+            host_timing: false,
+            last_frame_ms: None,
+        }
+    }
+
+    pub fn set_low_end_mode(&mut self, enabled: bool) {
+        self.renderer.set_low_end_mode(enabled);
+    }
+
+    pub fn set_sensitivity(&mut self, value: f32) {
+        self.renderer.set_sensitivity(value);
+    }
+
+    /// Restarts every random choice from `seed`; same seed and audio give the same frames.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.renderer.set_seed(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.renderer.seed()
+    }
+
+    /// Renders at `scale` x canvas size (0.25..=1) and upscales into the framebuffer.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        self.renderer.set_render_scale(scale);
+    }
+
+    /// "nearest" for a pixel look, "bilinear" for soft.
+    pub fn set_upscale_filter(&mut self, name: &str) -> Result<(), JsValue> {
+        let filter = UpscaleFilter::from_name(name).map_err(|e| JsValue::from_str(&e))?;
+        self.renderer.set_upscale_filter(filter);
+        Ok(())
+    }
+
+    /// "aliased" (cheapest), "smooth-add" or "smooth-max"; low-end mode stays aliased.
+    pub fn set_line_mode(&mut self, name: &str) -> Result<(), JsValue> {
+        let mode = LineMode::from_name(name).map_err(|e| JsValue::from_str(&e))?;
+        self.renderer.set_line_mode(mode);
+        Ok(())
+    }
+
+    /// "round" or "square" ends on lines wider than one pixel.
+    pub fn set_line_cap(&mut self, name: &str) -> Result<(), JsValue> {
+        let cap = LineCap::from_name(name).map_err(|e| JsValue::from_str(&e))?;
+        self.renderer.set_line_cap(cap);
+        Ok(())
+    }
+
+    /// "none" (wireframe), "flat" or "depth"-shaded solid faces under the lines.
+    pub fn set_face_fill(&mut self, name: &str) -> Result<(), JsValue> {
+        let fill = FaceFill::from_name(name).map_err(|e| JsValue::from_str(&e))?;
+        self.renderer.set_face_fill(fill);
+        Ok(())
+    }
+
+    /// A built-in palette name (see `palette_names`) or "#rrggbb" stops,
+    /// optionally placed with "@position", e.g. "#ff0080,#00e0ff@0.4".
+    pub fn set_palette(&mut self, spec: &str) -> Result<(), JsValue> {
+        let palette = Palette::parse(spec).map_err(|e| JsValue::from_str(&e))?;
+        self.renderer.set_palette(palette);
+        Ok(())
+    }
+
+    /// The current palette as "#rrggbb@position" stops.
+    pub fn palette(&self) -> String {
+        self.renderer.palette().to_spec()
+    }
+
+    pub fn palette_names(&self) -> Vec<String> {
+        PALETTE_NAMES.iter().map(|&n| n.to_string()).collect()
+    }
+
+    /// "sine" (the classic oscillators), or "time", "energy" or "depth" to pick from the palette.
+    pub fn set_palette_mode(&mut self, name: &str) -> Result<(), JsValue> {
+        let mode = PaletteMode::from_name(name).map_err(|e| JsValue::from_str(&e))?;
+        self.renderer.set_palette_mode(mode);
+        Ok(())
+    }
+
+    pub fn palette_mode(&self) -> String {
+        self.renderer.palette_mode().name().to_string()
+    }
+
+    /// Switches the visual mode by name (see `scene_names`); "tunnel" is the default.
+    pub fn set_scene(&mut self, name: &str) -> Result<(), JsValue> {
+        self.renderer.set_scene(name).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn scene_names(&self) -> Vec<String> {
+        self.renderer.scene_names().into_iter().map(String::from).collect()
+    }
+
+    /// Post-processing pass names in the order they run.
+    pub fn post_passes(&self) -> Vec<String> {
+        self.renderer.post_chain().passes().iter().map(|p| p.kind().name().to_string()).collect()
+    }
+
+    /// Inserts an enabled "glow", "smoothing", "darken", "scanlines" or "invert" pass at `index`.
+    pub fn add_post_pass(&mut self, index: usize, name: &str) -> Result<(), JsValue> {
+        let kind = PostKind::from_name(name).map_err(|e| JsValue::from_str(&e))?;
+        self.renderer.post_chain_mut().insert(index, kind);
+        Ok(())
+    }
+
+    pub fn remove_post_pass(&mut self, index: usize) -> Result<(), JsValue> {
+        self.renderer.post_chain_mut().remove(index).map(|_| ()).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn move_post_pass(&mut self, from: usize, to: usize) -> Result<(), JsValue> {
+        self.renderer.post_chain_mut().move_pass(from, to).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn set_post_pass_enabled(&mut self, index: usize, enabled: bool) -> Result<(), JsValue> {
+        let pass = self.renderer.post_chain_mut().get_mut(index).map_err(|e| JsValue::from_str(&e))?;
+        pass.set_enabled(enabled);
+        Ok(())
+    }
+
+    /// Sets a parameter of the pass at `index`, e.g. "period" on scanlines.
+    pub fn set_post_param(&mut self, index: usize, name: &str, value: f32) -> Result<(), JsValue> {
+        let pass = self.renderer.post_chain_mut().get_mut(index).map_err(|e| JsValue::from_str(&e))?;
+        pass.set_param(name, value).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Hides geometry behind nearer lines and faces; low-end mode skips the test.
+    pub fn set_depth_test(&mut self, enabled: bool) {
+        self.renderer.set_depth_test(enabled);
+    }
+
+    pub fn set_performance_mode(&mut self, mode: u8) {
+        self.renderer.set_performance_mode(mode);
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.renderer.resize(width, height);
+    }
+
+    /// Sets a tunnel parameter by name (see `param_names`); errors name the valid range.
+    pub fn set_param(&mut self, name: &str, value: f32) -> Result<(), JsValue> {
+        self.renderer.set_param(name, value).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn get_param(&self, name: &str) -> Result<f32, JsValue> {
+        self.renderer.params().get(name).ok_or_else(|| JsValue::from_str(&format!("unknown parameter {:?}", name)))
+    }
+
+    pub fn param_names(&self) -> Vec<String> {
+        PARAM_SPECS.iter().map(|s| s.name.to_string()).collect()
+    }
+
+    /// Binds the DOM VU meter inside the element with `container_id`.
+    /// Only one visualizer per page should drive it.
+    pub fn attach_vu_bars(&mut self, container_id: &str) {
+        if self.vu_bars.is_some() {
+            return;
+        }
+        if let Some(doc) = window().and_then(|w| w.document()) {
+            if let Some(container) = doc.get_element_by_id(container_id) {
+                container.set_inner_html("");
+                let mut segs: Vec<HtmlElement> = Vec::new();
+                let mut cuts: Vec<HtmlElement> = Vec::new();
+                let mut lows: Vec<HtmlElement> = Vec::new();
+                let mut mids: Vec<HtmlElement> = Vec::new();
+                let mut highs: Vec<HtmlElement> = Vec::new();
+                for _ in 0..24 {
+                    let bar = doc.create_element("div").unwrap(); bar.set_attribute("class", "bar").ok();
+                    let seg = doc.create_element("div").unwrap(); seg.set_attribute("class", "seg").ok();
+                    let low = doc.create_element("div").unwrap(); low.set_attribute("class", "seg low").ok();
+                    let mid = doc.create_element("div").unwrap(); mid.set_attribute("class", "seg mid").ok();
+                    let high = doc.create_element("div").unwrap(); high.set_attribute("class", "seg high").ok();
+                    let cut = doc.create_element("div").unwrap(); cut.set_attribute("class", "cut").ok();
+                    seg.append_child(&low).ok(); seg.append_child(&mid).ok(); seg.append_child(&high).ok();
+                    bar.append_child(&seg).ok(); bar.append_child(&cut).ok(); container.append_child(&bar).ok();
+                    segs.push(seg.unchecked_into()); cuts.push(cut.unchecked_into());
+                    lows.push(low.unchecked_into()); mids.push(mid.unchecked_into()); highs.push(high.unchecked_into());
+                }
+                self.vu_bars = Some(VuBars {
+                    segs, cuts, lows, mids, highs,
+                    prev_low: vec![0.0; 24],
+                    prev_mid: vec![0.0; 24],
+                    prev_high: vec![0.0; 24],
+                });
+            }
+        }
+    }
+
+    /// Rebuilds the LCD screen text; the mini VU and preset buttons it creates
+    /// are driven by this visualizer.
+    pub fn set_screen_text(&mut self, s: &str) {
+        if let Some(widgets) = build_screen_text(s, self.presets.active()) {
+            self.mini_vu_blocks = Some(widgets.mini_vu_blocks);
+            self.preset_buttons = Some(widgets.preset_buttons);
+        }
+    }
+
+    /// Recalls preset `slot` (0-3) and highlights its button.
+    pub fn recall_preset(&mut self, slot: usize) -> Result<(), JsValue> {
+        self.presets.recall(slot, &mut self.renderer).map_err(|e| JsValue::from_str(&e))?;
+        self.sync_preset_buttons();
+        Ok(())
+    }
+
+    /// Stores the current look into preset `slot` (0-3) under `name`.
+    pub fn store_preset(&mut self, slot: usize, name: &str) -> Result<(), JsValue> {
+        let preset = Preset::capture(&self.renderer, name);
+        self.presets.store(slot, preset).map_err(|e| JsValue::from_str(&e))?;
+        self.sync_preset_buttons();
+        Ok(())
+    }
+
+    /// Current look as a versioned preset document, named after the active slot.
+    pub fn export_preset(&self) -> String {
+        let name = self.presets.active().and_then(|i| self.presets.get(i)).map_or("CUSTOM", |p| p.name.as_str());
+        Preset::capture(&self.renderer, name).to_json()
+    }
+
+    /// Applies a preset document; the renderer is untouched if it is invalid.
+    /// The look no longer matches a slot, so no button stays highlighted.
+    pub fn import_preset(&mut self, text: &str) -> Result<(), JsValue> {
+        let preset = Preset::from_json(text).map_err(|e| JsValue::from_str(&e))?;
+        preset.apply(&mut self.renderer).map_err(|e| JsValue::from_str(&e))?;
+        self.presets.clear_active();
+        self.sync_preset_buttons();
+        Ok(())
+    }
+
+    pub fn active_preset(&self) -> Option<usize> {
+        self.presets.active()
+    }
+
+    pub fn preset_name(&self, slot: usize) -> Option<String> {
+        self.presets.get(slot).map(|p| p.name.clone())
+    }
+
+    pub fn sensitivity(&self) -> f32 {
+        self.renderer.sensitivity()
+    }
+
+    pub fn update_vu(&mut self, buf: &[u8]) {
+        self.renderer.set_spectrum(buf);
+    }
+
+    /// Feeds consecutive mono samples (-1..1) through the in-crate analyser
+    /// instead of taking AnalyserNode bytes.
+    pub fn update_pcm(&mut self, samples: &[f32], sample_rate: f32) -> Result<(), JsValue> {
+        if self.analyser.as_ref().map(|a| a.sample_rate()) != Some(sample_rate) {
+            self.analyser = Some(Analyser::new(self.analysis_config.clone(), sample_rate).map_err(|e| JsValue::from_str(&e))?);
+            self.renderer.set_sample_rate(sample_rate);
+        }
+        let analyser = self.analyser.as_mut().unwrap();
+        analyser.push(samples);
+        self.renderer.set_spectrum(analyser.analyse());
+        Ok(())
+    }
+
+    /// Configures `update_pcm`. `window` is one of rectangular, hann, hamming, blackman;
+    /// `attack`/`release` are smoothing time constants in seconds.
+    pub fn set_analysis(&mut self, fft_size: usize, window: &str, attack: f32, release: f32, min_db: f32, max_db: f32) -> Result<(), JsValue> {
+        let config = AnalysisConfig {
+            fft_size,
+            window: Window::from_name(window).map_err(|e| JsValue::from_str(&e))?,
+            attack,
+            release,
+            min_db,
+            max_db,
+        };
+        config.validate().map_err(|e| JsValue::from_str(&e))?;
+        self.analysis_config = config;
+        self.analyser = None;
+        Ok(())
+    }
+
+    pub fn update_mouse(&mut self, x: f32, y: f32, active: bool, down: bool) {
+        self.renderer.set_mouse(x, y, active, down);
+    }
+
+    pub fn frame_ptr(&self) -> *const u8 {
+        self.renderer.frame().as_ptr()
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.renderer.frame().len()
+    }
+
+    /// Renders a frame, advancing the animation by the time since the previous call.
+    pub fn render_frame(&mut self) {
+        let now = now_ms();
+        if let (Some(now), Some(last)) = (now, self.last_frame_ms) {
+            self.renderer.set_frame_interval(((now - last) / 1000.0) as f32);
+        }
+        self.last_frame_ms = now;
+        self.draw(now);
+    }
+
+    /// Renders a frame that comes `dt` seconds after the previous one, for hosts
+    /// that keep their own clock (e.g. the requestAnimationFrame timestamp).
+    pub fn render_frame_dt(&mut self, dt: f32) {
+        self.renderer.set_frame_interval(dt);
+        self.last_frame_ms = None;
+        let now = if self.governor.is_some() && !self.host_timing { now_ms() } else { None };
+        self.draw(now);
+    }
+
+    fn draw(&mut self, now: Option<f64>) {
+        let start = if self.governor.is_some() && !self.host_timing { now } else { None };
+        self.renderer.render();
+        if let (Some(start), Some(end)) = (start, start.and_then(|_| now_ms())) {
+            self.record_frame_time((end - start) as f32);
+        }
+        if self.renderer.width() == 0 || self.renderer.height() == 0 { return; }
+        self.update_dom_vu();
+    }
+
+    /// Sample rate of the audio behind `update_vu` spectra (e.g. `AudioContext.sampleRate`),
+    /// so kick/snare/hat bands map to the right bins. `update_pcm` sets it itself.
+    pub fn set_sample_rate(&mut self, hz: f32) {
+        self.renderer.set_sample_rate(hz);
+    }
+
+    /// Drum hits since the last call, oldest first, flattened as
+    /// `[kind, strength, time, ...]` with kind 0 = kick, 1 = snare, 2 = hat,
+    /// strength 0..1 and time in seconds of rendered audio.
+    pub fn take_transients(&mut self) -> Vec<f32> {
+        let mut out = Vec::new();
+        for hit in self.renderer.transients_mut().drain() {
+            let kind = DrumKind::ALL.iter().position(|&k| k == hit.kind).unwrap();
+            out.extend_from_slice(&[kind as f32, hit.strength, hit.time]);
+        }
+        out
+    }
+
+    /// Tracked tempo in beats per minute, 0 until a steady beat is found.
+    pub fn bpm(&self) -> f32 {
+        self.renderer.tempo().bpm()
+    }
+
+    /// 0 on the beat, rising toward 1 until the next one.
+    pub fn beat_phase(&self) -> f32 {
+        self.renderer.tempo().beat_phase()
+    }
+
+    /// 0..1, how steady the beat behind `bpm` is.
+    pub fn bpm_confidence(&self) -> f32 {
+        self.renderer.tempo().confidence()
+    }
+
+    /// Lets quality follow frame cost, aiming for `target_ms` per frame.
+    /// Disabling it keeps whatever level was reached.
+    pub fn set_auto_quality(&mut self, enabled: bool, target_ms: f32) -> Result<(), JsValue> {
+        if !enabled {
+            self.governor = None;
+            return Ok(());
+        }
+        match self.governor.as_mut() {
+            Some(governor) => governor.set_target_ms(target_ms).map_err(|e| JsValue::from_str(&e))?,
+            None => {
+                let governor = Governor::new(target_ms).map_err(|e| JsValue::from_str(&e))?;
+                governor.apply(&mut self.renderer);
+                self.governor = Some(governor);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reports how long the host spent producing the last frame (render plus
+    /// blit, not the rAF interval). Once called, the visualizer stops timing itself.
+    pub fn report_frame_time(&mut self, ms: f32) {
+        self.host_timing = true;
+        self.record_frame_time(ms);
+    }
+
+    /// Current adaptive quality level, 0 = best; `None` while auto quality is off.
+    pub fn quality_level(&self) -> Option<usize> {
+        self.governor.as_ref().map(|g| g.level())
+    }
+
+    pub fn quality_name(&self) -> Option<String> {
+        self.governor.as_ref().map(|g| g.quality().name.to_string())
+    }
+}
+
+impl Visualizer {
+    fn record_frame_time(&mut self, ms: f32) {
+        if let Some(governor) = self.governor.as_mut() {
+            governor.record_render(&mut self.renderer, ms);
+        }
+    }
+
+    fn sync_preset_buttons(&self) {
+        if let Some(buttons) = self.preset_buttons.as_ref() {
+            highlight_preset(buttons, self.presets.active());
+        }
+    }
+
+    fn update_dom_vu(&mut self) {
+        let vu_data = self.renderer.spectrum();
+        if vu_data.is_empty() { return; }
+        // Update VU DOM heights every frame (smooth)
+        let release = 0.85f32.powf(self.renderer.frame_interval() * REFERENCE_HZ);
+        if let Some(bars) = self.vu_bars.as_mut() {
+            let cols = bars.segs.len();
+            let bins = vu_data.len().max(1);
+            let bands_per_col = 3usize;
+            let buckets_total = cols * bands_per_col;
+            let bucket_size = bins.div_ceil(buckets_total).max(1);
+            let quant = |pct: f32| -> f32 { let step = 6.0f32; (pct / step).floor() * step };
+            for i in 0..cols {
+                let base = (i * bands_per_col * bucket_size).min(bins - 1);
+                let mut acc_low = 0u32; let mut acc_mid = 0u32; let mut acc_high = 0u32;
+                for k in 0..bucket_size { let idx = base + k; if idx < bins { acc_low += vu_data[idx] as u32; } }
+                for k in 0..bucket_size { let idx = base + bucket_size + k; if idx < bins { acc_mid += vu_data[idx] as u32; } }
+                for k in 0..bucket_size { let idx = base + bucket_size*2 + k; if idx < bins { acc_high += vu_data[idx] as u32; } }
+                let mut v_low = (acc_low as f32 / bucket_size as f32) / 255.0;
+                let mut v_mid = (acc_mid as f32 / bucket_size as f32) / 255.0;
+                let mut v_high = (acc_high as f32 / bucket_size as f32) / 255.0;
+                let noise = 0.12f32;
+                v_low = (v_low - noise).max(0.0) * 0.9;
+                v_mid = (v_mid - noise).max(0.0) * 0.8;
+                v_high = (v_high - noise).max(0.0) * 0.7;
+                let mut h_low = quant(v_low * 100.0).max(2.0);
+                let mut h_mid = quant(v_mid * 100.0).max(0.0);
+                let mut h_high = quant(v_high * 100.0).max(0.0);
+                h_low = h_low.max(bars.prev_low[i] * release);
+                h_mid = h_mid.max(bars.prev_mid[i] * release);
+                h_high = h_high.max(bars.prev_high[i] * release);
+                bars.prev_low[i] = h_low; bars.prev_mid[i] = h_mid; bars.prev_high[i] = h_high;
+                let mut total_h = h_low + h_mid + h_high;
+                if total_h > 100.0 {
+                    let scale = 100.0 / total_h;
+                    h_low = quant(h_low * scale);
+                    h_mid = quant(h_mid * scale);
+                    h_high = quant(h_high * scale);
+                    total_h = (h_low + h_mid + h_high).min(100.0);
+                }
+                bars.segs[i].style().set_property("height", "100%").ok();
+                bars.cuts[i].style().set_property("bottom", &format!("{}%", total_h.min(100.0))).ok();
+                bars.lows[i].style().set_property("height", &format!("{}%", h_low)).ok();
+                bars.mids[i].style().set_property("height", &format!("{}%", h_mid)).ok();
+                bars.highs[i].style().set_property("height", &format!("{}%", h_high)).ok();
+                bars.lows[i].style().set_property("bottom", "0%" ).ok();
+                let mid_bottom = h_low.min(100.0);
+                bars.mids[i].style().set_property("bottom", &format!("{}%", mid_bottom)).ok();
+                let high_bottom = (h_low + h_mid).min(100.0);
+                bars.highs[i].style().set_property("bottom", &format!("{}%", high_bottom)).ok();
+            }
+        } // end VU bars
+
+        if let Some(blocks) = self.mini_vu_blocks.as_ref() {
+            let bins = vu_data.len().max(1);
+            let cols = blocks.len();
+            let bucket = bins.div_ceil(cols).max(1);
+            for (i, el) in blocks.iter().enumerate() {
+                let mut acc = 0u32;
+                for k in 0..bucket { let idx = i * bucket + k; if idx < bins { acc += vu_data[idx] as u32; } }
+                let v = (acc as f32 / bucket as f32) / 255.0;
+                let on = v > 0.12;
+                let s = el.style();
+                if on {
+                    s.set_property("background", "#ffd76a").ok();
+                } else {
+                    s.set_property("background", "rgba(255,224,122,.12)").ok();
+                }
+            }
+        }
+    }
+}
+
+thread_local! {
+    // Instance behind the legacy free-function API
+    static DEFAULT: RefCell<Visualizer> = RefCell::new(Visualizer::new(0, 0));
+}
+
+fn with_default<R>(f: impl FnOnce(&mut Visualizer) -> R) -> R {
+    DEFAULT.with(|v| f(&mut v.borrow_mut()))
+}
+
+#[wasm_bindgen]
+pub fn set_low_end_mode(enabled: bool) {
+    with_default(|v| v.set_low_end_mode(enabled));
+}
+
+#[wasm_bindgen]
+pub fn set_sensitivity(value: f32) {
+    with_default(|v| v.set_sensitivity(value));
+}
+
+#[wasm_bindgen]
+pub fn set_seed(seed: u64) {
+    with_default(|v| v.set_seed(seed));
+}
+
+#[wasm_bindgen]
+pub fn seed() -> u64 {
+    with_default(|v| v.seed())
+}
+
+#[wasm_bindgen]
+pub fn set_render_scale(scale: f32) {
+    with_default(|v| v.set_render_scale(scale));
+}
+
+#[wasm_bindgen]
+pub fn set_upscale_filter(name: &str) -> Result<(), JsValue> {
+    with_default(|v| v.set_upscale_filter(name))
+}
+
+#[wasm_bindgen]
+pub fn set_line_mode(name: &str) -> Result<(), JsValue> {
+    with_default(|v| v.set_line_mode(name))
+}
+
+#[wasm_bindgen]
+pub fn set_line_cap(name: &str) -> Result<(), JsValue> {
+    with_default(|v| v.set_line_cap(name))
+}
+
+#[wasm_bindgen]
+pub fn set_face_fill(name: &str) -> Result<(), JsValue> {
+    with_default(|v| v.set_face_fill(name))
+}
+
+#[wasm_bindgen]
+pub fn set_palette(spec: &str) -> Result<(), JsValue> {
+    with_default(|v| v.set_palette(spec))
+}
+
+#[wasm_bindgen]
+pub fn palette() -> String {
+    with_default(|v| v.palette())
+}
+
+#[wasm_bindgen]
+pub fn palette_names() -> Vec<String> {
+    with_default(|v| v.palette_names())
+}
+
+#[wasm_bindgen]
+pub fn set_palette_mode(name: &str) -> Result<(), JsValue> {
+    with_default(|v| v.set_palette_mode(name))
+}
+
+#[wasm_bindgen]
+pub fn palette_mode() -> String {
+    with_default(|v| v.palette_mode())
+}
+
+#[wasm_bindgen]
+pub fn set_scene(name: &str) -> Result<(), JsValue> {
+    with_default(|v| v.set_scene(name))
+}
+
+#[wasm_bindgen]
+pub fn scene_names() -> Vec<String> {
+    with_default(|v| v.scene_names())
+}
+
+#[wasm_bindgen]
+pub fn post_passes() -> Vec<String> {
+    with_default(|v| v.post_passes())
+}
+
+#[wasm_bindgen]
+pub fn add_post_pass(index: usize, name: &str) -> Result<(), JsValue> {
+    with_default(|v| v.add_post_pass(index, name))
+}
+
+#[wasm_bindgen]
+pub fn remove_post_pass(index: usize) -> Result<(), JsValue> {
+    with_default(|v| v.remove_post_pass(index))
+}
+
+#[wasm_bindgen]
+pub fn move_post_pass(from: usize, to: usize) -> Result<(), JsValue> {
+    with_default(|v| v.move_post_pass(from, to))
+}
+
+#[wasm_bindgen]
+pub fn set_post_pass_enabled(index: usize, enabled: bool) -> Result<(), JsValue> {
+    with_default(|v| v.set_post_pass_enabled(index, enabled))
+}
+
+#[wasm_bindgen]
+pub fn set_post_param(index: usize, name: &str, value: f32) -> Result<(), JsValue> {
+    with_default(|v| v.set_post_param(index, name, value))
+}
+
+#[wasm_bindgen]
+pub fn set_depth_test(enabled: bool) {
+    with_default(|v| v.set_depth_test(enabled));
+}
+
+#[wasm_bindgen]
+pub fn set_performance_mode(mode: u8) {
+    with_default(|v| v.set_performance_mode(mode));
+}
+
+#[wasm_bindgen]
+pub fn set_param(name: &str, value: f32) -> Result<(), JsValue> {
+    with_default(|v| v.set_param(name, value))
+}
+
+#[wasm_bindgen]
+pub fn get_param(name: &str) -> Result<f32, JsValue> {
+    with_default(|v| v.get_param(name))
+}
+
+#[wasm_bindgen]
+pub fn param_names() -> Vec<String> {
+    with_default(|v| v.param_names())
+}
+
+#[wasm_bindgen]
+pub fn wasm_init_canvas(width: u32, height: u32) {
+    with_default(|v| {
+        v.resize(width, height);
+        v.attach_vu_bars("vuBars");
+    });
+}
+
+#[wasm_bindgen]
+pub fn wasm_update_vu(buf: &[u8]) {
+    with_default(|v| v.update_vu(buf));
+}
+
+#[wasm_bindgen]
+pub fn wasm_update_pcm(samples: &[f32], sample_rate: f32) -> Result<(), JsValue> {
+    with_default(|v| v.update_pcm(samples, sample_rate))
+}
+
+#[wasm_bindgen]
+pub fn set_analysis(fft_size: usize, window: &str, attack: f32, release: f32, min_db: f32, max_db: f32) -> Result<(), JsValue> {
+    with_default(|v| v.set_analysis(fft_size, window, attack, release, min_db, max_db))
+}
+
+#[wasm_bindgen]
+pub fn wasm_update_mouse(x: f32, y: f32, active: bool, down: bool) {
+    with_default(|v| v.update_mouse(x, y, active, down));
+}
+
+#[wasm_bindgen]
+pub fn frame_ptr() -> *const u8 {
+    with_default(|v| v.frame_ptr())
+}
+
+#[wasm_bindgen]
+pub fn frame_len() -> usize {
+    with_default(|v| v.frame_len())
+}
+
+#[wasm_bindgen]
+pub fn wasm_set_screen_text(s: &str) {
+    with_default(|v| v.set_screen_text(s));
+}
+
+#[wasm_bindgen]
+pub fn recall_preset(slot: usize) -> Result<(), JsValue> {
+    with_default(|v| v.recall_preset(slot))
+}
+
+#[wasm_bindgen]
+pub fn store_preset(slot: usize, name: &str) -> Result<(), JsValue> {
+    with_default(|v| v.store_preset(slot, name))
+}
+
+#[wasm_bindgen]
+pub fn export_preset() -> String {
+    with_default(|v| v.export_preset())
+}
+
+#[wasm_bindgen]
+pub fn import_preset(text: &str) -> Result<(), JsValue> {
+    with_default(|v| v.import_preset(text))
+}
+
+#[wasm_bindgen]
+pub fn active_preset() -> Option<usize> {
+    with_default(|v| v.active_preset())
+}
+
+#[wasm_bindgen]
+pub fn preset_name(slot: usize) -> Option<String> {
+    with_default(|v| v.preset_name(slot))
+}
+
+#[wasm_bindgen]
+pub fn get_sensitivity() -> f32 {
+    with_default(|v| v.sensitivity())
+}
+
+#[wasm_bindgen]
+pub fn set_sample_rate(hz: f32) {
+    with_default(|v| v.set_sample_rate(hz));
+}
+
+#[wasm_bindgen]
+pub fn take_transients() -> Vec<f32> {
+    with_default(|v| v.take_transients())
+}
+
+#[wasm_bindgen]
+pub fn bpm() -> f32 {
+    with_default(|v| v.bpm())
+}
+
+#[wasm_bindgen]
+pub fn beat_phase() -> f32 {
+    with_default(|v| v.beat_phase())
+}
+
+#[wasm_bindgen]
+pub fn bpm_confidence() -> f32 {
+    with_default(|v| v.bpm_confidence())
+}
+
+#[wasm_bindgen]
+pub fn set_auto_quality(enabled: bool, target_ms: f32) -> Result<(), JsValue> {
+    with_default(|v| v.set_auto_quality(enabled, target_ms))
+}
+
+#[wasm_bindgen]
+pub fn report_frame_time(ms: f32) {
+    with_default(|v| v.report_frame_time(ms));
+}
+
+#[wasm_bindgen]
+pub fn quality_level() -> Option<usize> {
+    with_default(|v| v.quality_level())
+}
+
+#[wasm_bindgen]
+pub fn quality_name() -> Option<String> {
+    with_default(|v| v.quality_name())
+}
+
+#[wasm_bindgen]
+pub fn wasm_render_frame() {
+    with_default(|v| v.render_frame());
+}
+
+#[wasm_bindgen]
+pub fn wasm_render_frame_dt(dt: f32) {
+    with_default(|v| v.render_frame_dt(dt));
+}
+
+// Widgets created by `build_screen_text` that the visualizer keeps updating
+struct ScreenWidgets {
+    mini_vu_blocks: Vec<HtmlElement>,
+    preset_buttons: Vec<HtmlElement>,
+}
+
+// Builds the LCD text, labels and preset row; returns the live widgets if they were created.
+fn build_screen_text(s: &str, active_preset: Option<usize>) -> Option<ScreenWidgets> {
+    let mut widgets = None;
+    let window = window().expect("no window");
+    let document: Document = window.document().expect("no document");
+    if let Some(screen) = document.get_element_by_id("screenText") {
+        screen.set_inner_html("");
+        for (i, ch) in s.chars().enumerate() {
+            let span = document.create_element("span").unwrap();
+            span.set_attribute("class", "glyph").ok();
+            span.set_text_content(Some(&ch.to_string()));
+            span.set_attribute("style", &format!("animation-delay: {}s", (i as f32) * 0.1)).ok();
+            screen.append_child(&span).ok();
+        }
+    }
+    if let Some(cells) = document.get_element_by_id("textCells") {
+        cells.set_inner_html("");
+        for (i, ch) in s.chars().enumerate() {
+            let div = document.create_element("div").unwrap();
+            let cls = if ch == ' ' { "cell space" } else { "cell" };
+            div.set_attribute("class", cls).ok();
+            div.set_attribute("style", &format!("animation-delay: {}s", (i as f32) * 0.1)).ok();
+            cells.append_child(&div).ok();
+        }
+    }
+    if document.get_element_by_id("lcdLabels").is_none() {
+        if let Some(upper) = document.get_element_by_id("upperOverlay") {
+            let labels = document.create_element("div").unwrap();
+            labels.set_attribute("id", "lcdLabels").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("position", "absolute").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("left", "45%").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("top", "7.8vw").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("color", "#ffe07a").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("font-family", "DigitalDreamSkew, monospace").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("font-size", "1.1vw").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("letter-spacing", "0").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("text-shadow", "0 0 0.6vw rgba(255,224,122,.45)").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("z-index", "2").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("display", "flex").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("gap", "2.2vw").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("opacity", "0").ok();
+            labels.dyn_ref::<HtmlElement>().unwrap().style().set_property("animation", "trackPowerOn 900ms ease-out forwards 150ms").ok();
+            for (idx, word) in ["MIX", "RPT", "SCN"].iter().enumerate() {
+                let span: HtmlElement = document.create_element("span").unwrap().unchecked_into();
+                span.set_text_content(Some(word));
+                span.style().set_property("opacity", "0").ok();
+                span.style().set_property("transform", "translateY(0.2vw)").ok();
+                let delay_ms = 180 + (idx as i32) * 80;
+                span.style().set_property("animation", &format!("glowIn 600ms ease-out forwards {}ms", delay_ms)).ok();
+                labels.append_child(&span).ok();
+            }
+            upper.append_child(&labels).ok();
+
+            if document.get_element_by_id("miniVu").is_none() {
+                let vu = document.create_element("div").unwrap();
+                vu.set_attribute("id", "miniVu").ok();
+                let style = vu.dyn_ref::<HtmlElement>().unwrap().style();
+                style.set_property("position", "absolute").ok();
+                style.set_property("left", "45%").ok();
+                style.set_property("top", "6.2vw").ok();
+                style.set_property("height", "0.6vw").ok();
+                style.set_property("right", "5%").ok();
+                style.set_property("display", "flex").ok();
+                style.set_property("gap", "0.02vw").ok();
+                style.set_property("align-items", "center").ok();
+                style.set_property("opacity", "0").ok();
+                style.set_property("animation", "trackPowerOn 700ms ease-out forwards 200ms").ok();
+
+                let mut blocks: Vec<HtmlElement> = Vec::new();
+                for _ in 0..19 {
+                    let b: HtmlElement = document.create_element("div").unwrap().unchecked_into();
+                    let bs = b.style();
+                    bs.set_property("width", "0.4vw").ok();
+                    bs.set_property("height", "100%").ok();
+                    bs.set_property("background", "rgba(255,224,122,.25)").ok();
+                    bs.set_property("box-shadow", "inset 0 0 2px rgba(0,0,0,.6)").ok();
+                    bs.set_property("border", "1px solid rgba(255,255,255,.08)").ok();
+                    vu.append_child(&b).ok();
+                    blocks.push(b);
+                }
+                let min_label: HtmlElement = document.create_element("div").unwrap().unchecked_into();
+                min_label.set_text_content(Some("MIN"));
+                let min_style = min_label.style();
+                min_style.set_property("position", "absolute").ok();
+                min_style.set_property("left", "calc(52% - 3vw)").ok();
+                min_style.set_property("top", "7.1vw").ok();
+                min_style.set_property("color", "#ffe07a").ok();
+                min_style.set_property("font-family", "DigitalDreamSkew, monospace").ok();
+                min_style.set_property("font-size", "0.5vw").ok();
+                min_style.set_property("text-shadow", "0 0 0.4vw rgba(255,224,122,.45)").ok();
+                min_style.set_property("opacity", "0").ok();
+                min_style.set_property("animation", "trackPowerOn 700ms ease-out forwards 200ms").ok();
+                upper.append_child(&min_label).ok();
+
+                let max_label: HtmlElement = document.create_element("div").unwrap().unchecked_into();
+                max_label.set_text_content(Some("MAX"));
+                let max_style = max_label.style();
+                max_style.set_property("position", "absolute").ok();
+                max_style.set_property("right", "calc(30% - 1vw)").ok();
+                max_style.set_property("top", "7.1vw").ok();
+                max_style.set_property("color", "#ffe07a").ok();
+                max_style.set_property("font-family", "DigitalDreamSkew, monospace").ok();
+                max_style.set_property("font-size", "0.5vw").ok();
+                max_style.set_property("text-shadow", "0 0 0.4vw rgba(255,224,122,.45)").ok();
+                max_style.set_property("opacity", "0").ok();
+                max_style.set_property("animation", "trackPowerOn 700ms ease-out forwards 200ms").ok();
+                upper.append_child(&max_label).ok();
+
+                upper.append_child(&vu).ok();
+
+                let bbe_label: HtmlElement = document.create_element("div").unwrap().unchecked_into();
+                bbe_label.set_text_content(Some("B.B.E. OFF"));
+                let bbe_style = bbe_label.style();
+                bbe_style.set_property("position", "absolute").ok();
+                bbe_style.set_property("left", "90%").ok();
+                bbe_style.set_property("top", "6.8vw").ok();
+                bbe_style.set_property("color", "#ffe07a").ok();
+                bbe_style.set_property("font-family", "DigitalDreamSkew, monospace").ok();
+                bbe_style.set_property("font-size", "0.6vw").ok();
+                bbe_style.set_property("text-shadow", "0 0 0.4vw rgba(255,224,122,.45)").ok();
+                bbe_style.set_property("opacity", "0").ok();
+                bbe_style.set_property("animation", "trackPowerOn 700ms ease-out forwards 300ms").ok();
+                upper.append_child(&bbe_label).ok();
+
+                let preset_container: HtmlElement = document.create_element("div").unwrap().unchecked_into();
+                let preset_style = preset_container.style();
+                preset_style.set_property("position", "absolute").ok();
+                preset_style.set_property("left", "90%").ok();
+                preset_style.set_property("top", "7.5vw").ok();
+                preset_style.set_property("display", "flex").ok();
+                preset_style.set_property("gap", "0.3vw").ok();
+                preset_style.set_property("opacity", "0").ok();
+                preset_style.set_property("animation", "trackPowerOn 700ms ease-out forwards 400ms").ok();
+
+                let mut preset_buttons: Vec<HtmlElement> = Vec::new();
+                for (i, &num) in ["1", "2", "3", "4"].iter().enumerate() {
+                    let btn: HtmlElement = document.create_element("div").unwrap().unchecked_into();
+                    btn.set_text_content(Some(num));
+                    // JS wires clicks through these: click recalls, shift+click stores
+                    btn.set_attribute("class", "presetBtn").ok();
+                    btn.set_attribute("data-slot", &i.to_string()).ok();
+                    let btn_style = btn.style();
+                    btn_style.set_property("color", "#ffe07a").ok();
+                    btn_style.set_property("font-family", "DigitalDreamSkew, monospace").ok();
+                    btn_style.set_property("font-size", "0.6vw").ok();
+                    btn_style.set_property("text-shadow", "0 0 0.4vw rgba(255,224,122,.45)").ok();
+                    btn_style.set_property("background", "rgba(255,224,122,.1)").ok();
+                    btn_style.set_property("border", "1px solid rgba(255,224,122,.3)").ok();
+                    btn_style.set_property("border-radius", "2px").ok();
+                    btn_style.set_property("padding", "0.2vw 0.4vw").ok();
+                    btn_style.set_property("box-shadow", "inset 0 0 2px rgba(0,0,0,.6)").ok();
+                    btn_style.set_property("cursor", "pointer").ok();
+                    // #upperOverlay disables pointer events for everything else inside it
+                    btn_style.set_property("pointer-events", "auto").ok();
+                    preset_container.append_child(&btn).ok();
+                    preset_buttons.push(btn);
+                }
+                highlight_preset(&preset_buttons, active_preset);
+
+                let preset_label: HtmlElement = document.create_element("div").unwrap().unchecked_into();
+                preset_label.set_text_content(Some("PRESET"));
+                let preset_label_style = preset_label.style();
+                preset_label_style.set_property("position", "absolute").ok();
+                preset_label_style.set_property("left", "calc(90% - 2.5vw)").ok();
+                preset_label_style.set_property("top", "7.9vw").ok();
+                preset_label_style.set_property("color", "#ffe07a").ok();
+                preset_label_style.set_property("font-family", "DigitalDreamSkew, monospace").ok();
+                preset_label_style.set_property("font-size", "0.5vw").ok();
+                preset_label_style.set_property("text-shadow", "0 0 0.4vw rgba(255,224,122,.45)").ok();
+                preset_label_style.set_property("opacity", "0").ok();
+                preset_label_style.set_property("animation", "trackPowerOn 700ms ease-out forwards 400ms").ok();
+                upper.append_child(&preset_label).ok();
+
+                upper.append_child(&preset_container).ok();
+                widgets = Some(ScreenWidgets { mini_vu_blocks: blocks, preset_buttons });
+            }
+        }
+    }
+    widgets
+}
+
+fn highlight_preset(buttons: &[HtmlElement], active: Option<usize>) {
+    for (i, btn) in buttons.iter().enumerate() {
+        let style = btn.style();
+        if active == Some(i) {
+            style.set_property("background", "rgba(255,224,122,.3)").ok();
+            style.set_property("border", "1px solid rgba(255,224,122,.6)").ok();
+        } else {
+            style.set_property("background", "rgba(255,224,122,.1)").ok();
+            style.set_property("border", "1px solid rgba(255,224,122,.3)").ok();
+        }
+    }
+}
+
+fn now_ms() -> Option<f64> {
+    Some(window()?.performance()?.now())
+}
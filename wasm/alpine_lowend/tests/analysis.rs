@@ -0,0 +1,49 @@
+use alpine_lowend_wasm::{AnalysisConfig, Analyser, Window};
+
+const RATE: f32 = 48000.0;
+
+fn sine(freq: f32, len: usize) -> Vec<f32> {
+    (0..len).map(|i| (std::f32::consts::TAU * freq * i as f32 / RATE).sin() * 0.5).collect()
+}
+
+#[test]
+fn sine_peaks_in_its_bin() {
+    let config = AnalysisConfig { fft_size: 1024, attack: 0.0, ..AnalysisConfig::default() };
+    let mut a = Analyser::new(config, RATE).unwrap();
+    // Bin 64 of a 1024-point FFT at 48 kHz
+    a.push(&sine(64.0 * RATE / 1024.0, 1024));
+    let spectrum = a.analyse();
+    assert_eq!(spectrum.len(), 512);
+    let peak = (0..spectrum.len()).max_by_key(|&k| spectrum[k]).unwrap();
+    assert_eq!(peak, 64);
+    assert!(spectrum[400] < spectrum[64] / 2);
+}
+
+#[test]
+fn silence_maps_to_floor() {
+    let mut a = Analyser::new(AnalysisConfig::default(), RATE).unwrap();
+    a.push(&[0.0; 4096]);
+    assert!(a.analyse().iter().all(|&v| v == 0));
+}
+
+#[test]
+fn release_holds_level_after_audio_stops() {
+    let config = AnalysisConfig { attack: 0.0, release: 1.0, ..AnalysisConfig::default() };
+    let mut a = Analyser::new(config, RATE).unwrap();
+    a.push(&sine(3000.0, 256));
+    let loud = a.analyse().to_vec();
+    a.push(&[0.0; 800]);
+    let after = a.analyse();
+    let bin = (3000.0 / RATE * 256.0) as usize;
+    assert!(after[bin] > 0 && after[bin] <= loud[bin]);
+}
+
+#[test]
+fn rejects_invalid_config() {
+    let bad_fft = AnalysisConfig { fft_size: 100, ..AnalysisConfig::default() };
+    assert!(Analyser::new(bad_fft, RATE).is_err());
+    let bad_range = AnalysisConfig { min_db: -10.0, max_db: -90.0, ..AnalysisConfig::default() };
+    assert!(Analyser::new(bad_range, RATE).is_err());
+    assert!(Analyser::new(AnalysisConfig::default(), 0.0).is_err());
+    assert!(Window::from_name("kaiser").is_err());
+}
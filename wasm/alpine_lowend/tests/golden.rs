@@ -0,0 +1,215 @@
+//! Golden-image tests: fixed synthetic spectra, seed and timestep rendered
+//! natively and compared against the PNGs in `tests/golden/`.
+//!
+//! A failing comparison writes `<name>.actual.png` and `<name>.diff.png` to
+//! cargo's test tmp dir (`target/tmp/golden/`). After an intended change to
+//! the look, regenerate the references with `ALPINE_BLESS=1 cargo test --test golden`
+//! and review the new images before committing them.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use alpine_lowend_wasm::{FaceFill, LineCap, LineMode, Renderer};
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 96;
+const SEED: u64 = 1;
+const FRAMES: u32 = 90;
+const BINS: usize = 1024;
+// A channel may drift this far before its pixel counts as changed...
+const CHANNEL_TOLERANCE: u8 = 8;
+// ...and this fraction of pixels may change before the image fails
+const PIXEL_TOLERANCE: f32 = 0.002;
+
+fn renderer() -> Renderer {
+    let mut r = Renderer::new(WIDTH, HEIGHT);
+    r.set_seed(SEED);
+    r.set_frame_interval(1.0 / 60.0);
+    r.set_sample_rate(48000.0);
+    r
+}
+
+// Deterministic jitter so the noise scene does not depend on any crate RNG
+struct Xorshift(u32);
+
+impl Xorshift {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32
+    }
+}
+
+// Pink noise falls 3 dB per octave; the analyser maps -90..-10 dB onto 0..255
+fn pink_noise(noise: &mut Xorshift) -> Vec<u8> {
+    (0..BINS)
+        .map(|bin| {
+            let db = -20.0 - 3.0 * ((bin + 1) as f32).log2() + (noise.next() - 0.5) * 12.0;
+            ((db + 90.0) / 80.0 * 255.0).clamp(0.0, 255.0) as u8
+        })
+        .collect()
+}
+
+// 120 BPM at 60 fps: a kick every 30 frames, decaying over a few frames, over a quiet bed
+fn kick_pattern(frame: u32) -> Vec<u8> {
+    let since = frame % 30;
+    let kick = if since < 4 { 230 - since * 40 } else { 0 };
+    (0..BINS).map(|bin| if bin < 8 { kick.max(40) as u8 } else { 20 }).collect()
+}
+
+fn render_silence() -> Vec<u8> {
+    let mut r = renderer();
+    for _ in 0..FRAMES {
+        r.set_spectrum(&[0u8; BINS]);
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+// Pink noise after `setup` picks the settings under test
+fn render_pink(setup: impl FnOnce(&mut Renderer)) -> Vec<u8> {
+    let mut r = renderer();
+    setup(&mut r);
+    let mut noise = Xorshift(0x2545_F491);
+    for _ in 0..FRAMES {
+        r.set_spectrum(&pink_noise(&mut noise));
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+// Kicks drive the speed push; the button goes down near the end for the reverse and inversion
+fn render_kick_pattern() -> Vec<u8> {
+    let mut r = renderer();
+    r.set_param("kick_speed", 4.0).unwrap();
+    for frame in 0..FRAMES {
+        r.set_mouse(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0, false, frame >= FRAMES - 12);
+        r.set_spectrum(&kick_pattern(frame));
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut reader = png::Decoder::new(file).read_info().map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(format!("{}: expected 8-bit RGBA", path.display()));
+    }
+    buf.truncate(info.buffer_size());
+    Ok((info.width, info.height, buf))
+}
+
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) {
+    let file = File::create(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().and_then(|mut w| w.write_image_data(rgba)).unwrap();
+}
+
+// Changed pixels in red over a dimmed copy of the reference
+fn diff_image(expected: &[u8], actual: &[u8]) -> (Vec<u8>, usize) {
+    let mut changed = 0;
+    let mut out = Vec::with_capacity(expected.len());
+    for (e, a) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        if e.iter().zip(a).any(|(x, y)| x.abs_diff(*y) > CHANNEL_TOLERANCE) {
+            changed += 1;
+            out.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 12) as u8;
+            out.extend_from_slice(&[luma, luma, luma, 255]);
+        }
+    }
+    (out, changed)
+}
+
+fn check(name: &str, actual: &[u8]) {
+    let reference = golden_dir().join(format!("{}.png", name));
+    if std::env::var_os("ALPINE_BLESS").is_some() {
+        fs::create_dir_all(golden_dir()).unwrap();
+        write_png(&reference, WIDTH, HEIGHT, actual);
+        return;
+    }
+    let (width, height, expected) = read_png(&reference)
+        .unwrap_or_else(|e| panic!("{} (run with ALPINE_BLESS=1 to create it)", e));
+    assert_eq!((width, height), (WIDTH, HEIGHT), "{}: reference size changed", name);
+
+    let (diff, changed) = diff_image(&expected, actual);
+    let allowed = ((WIDTH * HEIGHT) as f32 * PIXEL_TOLERANCE) as usize;
+    if changed > allowed {
+        let out = output_dir();
+        fs::create_dir_all(&out).unwrap();
+        write_png(&out.join(format!("{}.actual.png", name)), WIDTH, HEIGHT, actual);
+        write_png(&out.join(format!("{}.diff.png", name)), WIDTH, HEIGHT, &diff);
+        panic!(
+            "{}: {} pixels differ from {} (allowed {}); see {}",
+            name,
+            changed,
+            reference.display(),
+            allowed,
+            out.join(format!("{}.diff.png", name)).display()
+        );
+    }
+}
+
+#[test]
+fn golden_silence() {
+    check("silence", &render_silence());
+}
+
+#[test]
+fn golden_pink_noise() {
+    // Glow and temporal smoothing on, so the post passes are covered
+    check("pink_noise", &render_pink(|r| { r.set_performance_mode(1); r.set_glow(true) }));
+}
+
+#[test]
+fn golden_pink_noise_smooth_lines() {
+    // Anti-aliased lines at the half render scale low-end machines run at
+    check("pink_noise_smooth", &render_pink(|r| { r.set_line_mode(LineMode::SmoothMax); r.set_render_scale(0.5) }));
+}
+
+#[test]
+fn golden_wide_lines() {
+    // Wide, audio-modulated lines with square caps through the additive blend
+    check("wide_lines", &render_pink(|r| { r.set_param("mesh_thickness", 2.5).unwrap(); r.set_param("thickness_audio", 3.0).unwrap(); r.set_line_cap(LineCap::Square); r.set_line_mode(LineMode::SmoothAdd) }));
+}
+
+#[test]
+fn golden_solid_faces() {
+    // Depth-shaded solid faces with their edges on top
+    check("solid_faces", &render_pink(|r| r.set_face_fill(FaceFill::Depth)));
+}
+
+#[test]
+fn golden_depth_test() {
+    // Flat faces through the depth buffer, so near rows cover the ones behind them
+    check("depth_test", &render_pink(|r| { r.set_face_fill(FaceFill::Flat); r.set_depth_test(true) }));
+}
+
+#[test]
+fn golden_kick_pattern() {
+    check("kick_pattern", &render_kick_pattern());
+}
+
+#[test]
+fn diff_counts_only_changes_beyond_tolerance() {
+    let expected = [10, 10, 10, 255, 10, 10, 10, 255];
+    let actual = [10 + CHANNEL_TOLERANCE, 10, 10, 255, 10, 10, 40, 255];
+    let (diff, changed) = diff_image(&expected, &actual);
+    assert_eq!(changed, 1);
+    assert_eq!(&diff[4..], &[255, 0, 0, 255]);
+}
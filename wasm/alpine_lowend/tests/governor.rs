@@ -0,0 +1,81 @@
+use alpine_lowend_wasm::governor::{DEFAULT_QUALITY_LEVEL, QUALITY_LEVELS};
+use alpine_lowend_wasm::{Governor, Renderer};
+
+// Feeds `frames` samples of `ms`; returns how many level changes happened
+fn feed(g: &mut Governor, ms: f32, frames: usize) -> usize {
+    (0..frames).filter(|_| g.record(ms).is_some()).count()
+}
+
+#[test]
+fn slow_frames_step_down_to_the_cheapest_level() {
+    let mut g = Governor::new(10.0).unwrap();
+    assert_eq!(g.level(), DEFAULT_QUALITY_LEVEL);
+    feed(&mut g, 30.0, 25);
+    assert_eq!(g.level(), DEFAULT_QUALITY_LEVEL + 1);
+    feed(&mut g, 30.0, 1000);
+    assert_eq!(g.level(), QUALITY_LEVELS.len() - 1);
+}
+
+#[test]
+fn fast_frames_step_up_to_the_best_level() {
+    let mut g = Governor::new(10.0).unwrap();
+    feed(&mut g, 2.0, 10_000);
+    assert_eq!(g.level(), 0);
+}
+
+#[test]
+fn steady_load_inside_the_band_holds_the_level() {
+    let mut g = Governor::new(10.0).unwrap();
+    assert_eq!(feed(&mut g, 9.0, 5000), 0);
+    assert_eq!(g.level(), DEFAULT_QUALITY_LEVEL);
+}
+
+#[test]
+fn retrying_a_slow_level_backs_off() {
+    // Level 2 costs 14 ms, level 3 costs 6 ms against a 10 ms target
+    let mut g = Governor::new(10.0).unwrap();
+    let mut upgrades = 0;
+    for _ in 0..20_000 {
+        let cost = if g.level() <= 2 { 14.0 } else { 6.0 };
+        let before = g.level();
+        if let Some(level) = g.record(cost) {
+            if level < before {
+                upgrades += 1;
+            }
+        }
+        assert!(g.level() >= 2);
+    }
+    // Waits double from 120 frames up to the cap, so retries stay rare
+    assert!(upgrades <= 10, "{} upgrades", upgrades);
+}
+
+#[test]
+fn skipped_frames_do_not_lift_the_skip_level() {
+    // Rendered frames cost 12 ms against a 10 ms target, skipped ones nearly
+    // nothing; only the rendered ones may count, or the average halves
+    let mut r = Renderer::new(160, 120);
+    let mut g = Governor::new(10.0).unwrap();
+    feed(&mut g, 50.0, 10_000);
+    g.apply(&mut r);
+    let skip = QUALITY_LEVELS.len() - 1;
+    assert_eq!(g.level(), skip);
+    for _ in 0..5000 {
+        r.set_spectrum(&[150u8; 1024]);
+        r.render();
+        let cost = if r.frame_skipped() { 0.05 } else { 12.0 };
+        assert_eq!(g.record_render(&mut r, cost), None);
+    }
+    assert_eq!((g.level(), r.frame_skip()), (skip, 1));
+}
+
+#[test]
+fn apply_sets_renderer_quality() {
+    let mut r = Renderer::new(160, 120);
+    let mut g = Governor::new(10.0).unwrap();
+    feed(&mut g, 50.0, 10_000);
+    g.apply(&mut r);
+    let q = g.quality();
+    assert_eq!(r.render_scale(), q.render_scale);
+    assert_eq!(r.frame_skip(), q.frame_skip);
+    assert!(Governor::new(0.0).is_err());
+}
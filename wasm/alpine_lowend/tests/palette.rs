@@ -0,0 +1,117 @@
+use alpine_lowend_wasm::palette::{Stop, PALETTE_NAMES};
+use alpine_lowend_wasm::{Palette, PaletteMode, Preset, Renderer};
+
+fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+    a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-4)
+}
+
+#[test]
+fn gradient_interpolates_and_wraps() {
+    let p = Palette::parse("#ff0000,#0000ff").unwrap();
+    assert!(close(p.sample(0.0), [1.0, 0.0, 0.0]));
+    assert!(close(p.sample(0.25), [0.5, 0.0, 0.5]));
+    assert!(close(p.sample(0.5), [0.0, 0.0, 1.0]));
+    // Past the last stop it blends back into the first, so cycling is seamless
+    assert!(close(p.sample(0.75), [0.5, 0.0, 0.5]));
+    assert!(close(p.sample(1.0), p.sample(0.0)));
+    assert!(close(p.sample(-0.25), p.sample(0.75)));
+
+    let placed = Palette::parse("#000000@0.2,#ffffff@0.6").unwrap();
+    assert!(close(placed.sample(0.4), [0.5; 3]));
+    assert!(close(placed.sample(0.0), placed.sample(1.0)));
+    let single = Palette::parse("#336699").unwrap();
+    assert!(close(single.sample(0.9), single.sample(0.1)));
+}
+
+#[test]
+fn parses_names_and_stops_and_rejects_bad_ones() {
+    for name in PALETTE_NAMES {
+        let p = Palette::parse(name).unwrap();
+        assert_eq!(Palette::parse(&p.to_spec()).unwrap(), p);
+    }
+    let p = Palette::parse("#FF0080@0, #00e0ff@0.4").unwrap();
+    assert_eq!(p.stops()[1], Stop { position: 0.4, color: [0x00, 0xe0, 0xff] });
+    assert_eq!(Palette::parse(&p.to_spec()).unwrap(), p);
+
+    for bad in ["", "plaid", "#ff00", "#gg0000", "#ff0000@0.5,#00ff00@0.2", "#ff0000@0,#00ff00", "#ff0000@1.5"] {
+        assert!(Palette::parse(bad).is_err(), "{:?} should be rejected", bad);
+    }
+    assert!(Palette::new(Vec::new()).is_err());
+    assert!(PaletteMode::from_name("rainbow").is_err());
+    for mode in [PaletteMode::Sine, PaletteMode::Time, PaletteMode::Energy, PaletteMode::Depth] {
+        assert_eq!(PaletteMode::from_name(mode.name()).unwrap(), mode);
+    }
+}
+
+fn render(setup: impl Fn(&mut Renderer)) -> Vec<u8> {
+    let mut r = Renderer::new(160, 120);
+    setup(&mut r);
+    for _ in 0..8 {
+        r.set_spectrum(&[120u8; 1024]);
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+// Mean of each channel over the lit pixels
+fn tint(frame: &[u8]) -> [f32; 3] {
+    let lit: Vec<&[u8]> = frame.chunks(4).filter(|p| p[..3].iter().any(|&c| c > 0)).collect();
+    let mut sum = [0.0; 3];
+    for p in &lit {
+        for c in 0..3 {
+            sum[c] += p[c] as f32;
+        }
+    }
+    sum.map(|s| s / lit.len().max(1) as f32)
+}
+
+#[test]
+fn gradient_modes_take_the_palette_colors() {
+    let sine = render(|_| {});
+    // The palette alone changes nothing until a gradient mode picks from it
+    assert!(render(|r| r.set_palette(Palette::parse("#00ff00").unwrap())) == sine);
+
+    for mode in [PaletteMode::Time, PaletteMode::Energy, PaletteMode::Depth] {
+        let red = tint(&render(|r| {
+            r.set_palette(Palette::parse("#ff0000").unwrap());
+            r.set_palette_mode(mode);
+        }));
+        // Beat flashes whiten every channel alike, so green and blue stay equal
+        assert!(red[0] > red[1] * 2.0 && red[1] == red[2], "{} mode tinted {:?}", mode.name(), red);
+    }
+
+    // With a two-color gradient, depth mode spreads both colors over the rows
+    // while time mode shows one at a time
+    let two = |mode| {
+        tint(&render(|r| {
+            r.set_palette(Palette::parse("#ff0000,#0000ff").unwrap());
+            r.set_palette_mode(mode);
+            r.set_param("color_speed", 0.0).unwrap();
+        }))
+    };
+    let time = two(PaletteMode::Time);
+    let depth = two(PaletteMode::Depth);
+    assert!(time[0] > time[2] * 2.0, "time mode at position 0 should be red, got {:?}", time);
+    assert!(depth[2] > time[2] * 2.0, "depth mode should reach the blue stop, got {:?}", depth);
+    assert!(depth[2] > 10.0, "depth mode should reach the blue stop, got {:?}", depth);
+}
+
+#[test]
+fn presets_carry_the_palette() {
+    let mut r = Renderer::new(160, 120);
+    r.set_palette(Palette::parse("#ff0080@0,#00e0ff@0.4").unwrap());
+    r.set_palette_mode(PaletteMode::Depth);
+    let preset = Preset::capture(&r, "LABEL");
+    assert_eq!(Preset::from_json(&preset.to_json()).unwrap(), preset);
+
+    let mut other = Renderer::new(160, 120);
+    preset.apply(&mut other).unwrap();
+    assert_eq!(other.palette(), r.palette());
+    assert_eq!(other.palette_mode(), PaletteMode::Depth);
+
+    // Documents without palette keys leave the renderer's colors alone
+    Preset::from_json(r#"{"format": "alpine-preset", "version": 1}"#).unwrap().apply(&mut other).unwrap();
+    assert_eq!(other.palette_mode(), PaletteMode::Depth);
+    let bad = r##"{"format": "alpine-preset", "version": 2, "palette": "#12"}"##;
+    assert!(Preset::from_json(bad).unwrap_err().contains("#rrggbb"));
+}
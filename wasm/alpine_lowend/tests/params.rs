@@ -0,0 +1,35 @@
+use alpine_lowend_wasm::params::PARAM_SPECS;
+use alpine_lowend_wasm::{Renderer, TunnelParams};
+
+#[test]
+fn every_spec_round_trips() {
+    let mut p = TunnelParams::default();
+    p.validate().unwrap();
+    for spec in PARAM_SPECS {
+        let v = p.get(spec.name).unwrap();
+        p.set(spec.name, v).unwrap();
+    }
+    assert_eq!(p, TunnelParams::default());
+}
+
+#[test]
+fn rejects_out_of_range_and_unknown() {
+    let mut p = TunnelParams::default();
+    assert!(p.set("speed", -1.0).is_err());
+    assert!(p.set("segments", 12.5).is_err());
+    assert!(p.set("fog_near", 0.95).is_err());
+    assert!(p.set("no_such_param", 1.0).is_err());
+    assert_eq!(p, TunnelParams::default());
+}
+
+#[test]
+fn geometry_change_rebuilds_rows() {
+    let render = |segments: f32| {
+        let mut r = Renderer::new(160, 120);
+        r.set_param("segments", segments).unwrap();
+        r.set_spectrum(&[150u8; 1024]);
+        r.render();
+        r.frame().to_vec()
+    };
+    assert_ne!(render(64.0), render(24.0));
+}
@@ -0,0 +1,66 @@
+// The SIMD passes must match the scalar ones byte for byte, whichever the
+// `simd` feature selects for the renderer
+#![cfg(any(target_arch = "x86_64", all(target_arch = "wasm32", target_feature = "simd128")))]
+
+use alpine_lowend_wasm::pixel::{scalar, simd};
+use alpine_lowend_wasm::Rng;
+
+// Random RGBA bytes; 37 pixels leaves a tail after the 4-pixel steps
+fn noise(rng: &mut Rng) -> Vec<u8> {
+    (0..37 * 4).map(|_| rng.next_u64() as u8).collect()
+}
+
+// Runs both versions of a pass on copies of the same buffer
+fn same(seed: u64, pass: impl Fn(&mut [u8], bool)) {
+    let mut rng = Rng::new(seed);
+    let base = noise(&mut rng);
+    let (mut a, mut b) = (base.clone(), base);
+    pass(&mut a, false);
+    pass(&mut b, true);
+    assert_eq!(a, b);
+}
+
+#[test]
+fn clear_and_invert_match() {
+    same(1, |buf, fast| if fast { simd::clear(buf) } else { scalar::clear(buf) });
+    for value in [0, 1, 128, 200, 255] {
+        same(2, |buf, fast| if fast { simd::soft_invert(buf, value) } else { scalar::soft_invert(buf, value) });
+    }
+}
+
+#[test]
+fn scaling_matches() {
+    for factor in [0.0, 0.33, 0.85, 0.88, 0.999, 1.0, 1.15, 3.7] {
+        same(3, |buf, fast| if fast { simd::scale_rgb(buf, factor) } else { scalar::scale_rgb(buf, factor) });
+    }
+}
+
+#[test]
+fn smoothing_matches_and_keeps_history() {
+    for s in [0.0, 0.25, 0.5, 0.93, 1.0] {
+        let mut rng = Rng::new(4);
+        let (frame, prev) = (noise(&mut rng), noise(&mut rng));
+        let (mut a, mut a_prev) = (frame.clone(), prev.clone());
+        let (mut b, mut b_prev) = (frame, prev);
+        scalar::smooth(&mut a, &mut a_prev, s);
+        simd::smooth(&mut b, &mut b_prev, s);
+        assert_eq!(a, b);
+        assert_eq!(a_prev, b_prev);
+        assert_eq!(a, a_prev);
+    }
+}
+
+#[test]
+fn glow_compositing_matches() {
+    let mut rng = Rng::new(5);
+    let halo = noise(&mut rng);
+    for (boost, gain) in [(256, 0), (282, 152), (512, 1024), (300, 77)] {
+        same(6, |buf, fast| {
+            if fast {
+                simd::add_glow(buf, &halo, boost, gain)
+            } else {
+                scalar::add_glow(buf, &halo, boost, gain)
+            }
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use alpine_lowend_wasm::{DrawTarget, FrameInput, PostChain, PostKind, Renderer, Scene, SceneSetup};
+
+// A flat gray frame, so each pass's effect can be read off single pixels
+struct Gray;
+
+impl Scene for Gray {
+    fn init(&mut self, _setup: &mut SceneSetup) {}
+
+    fn update(&mut self, _frame: &FrameInput) {}
+
+    fn draw(&mut self, _frame: &FrameInput, target: &mut DrawTarget) {
+        for px in target.pixels.chunks_exact_mut(4) {
+            px[..3].fill(200);
+        }
+    }
+}
+
+fn gray_renderer() -> Renderer {
+    let mut r = Renderer::new(16, 12);
+    r.register_scene("gray", || Box::new(Gray));
+    r.set_scene("gray").unwrap();
+    r
+}
+
+// Red channel of the first pixel in each of the first rows, at full spectrum so darken is a no-op
+fn rows(r: &mut Renderer, count: usize) -> Vec<u8> {
+    r.set_spectrum(&[255u8; 1024]);
+    r.render();
+    (0..count).map(|y| r.frame()[y * 16 * 4]).collect()
+}
+
+#[test]
+fn default_chain_is_the_classic_look() {
+    let r = Renderer::new(16, 12);
+    let kinds: Vec<_> = r.post_chain().passes().iter().map(|p| p.kind()).collect();
+    assert_eq!(kinds, PostKind::ALL);
+    assert!(!r.glow_enabled() && !r.smoothing_enabled());
+    let mut r = gray_renderer();
+    assert_eq!(rows(&mut r, 4), [176, 200, 200, 176]);
+}
+
+#[test]
+fn passes_have_their_own_params_and_flags() {
+    let mut r = gray_renderer();
+    let scanlines = r.post_chain().passes().iter().position(|p| p.kind() == PostKind::Scanlines).unwrap();
+    let pass = r.post_chain_mut().get_mut(scanlines).unwrap();
+    assert_eq!(pass.param_names(), ["period", "level"]);
+    pass.set_param("period", 2.0).unwrap();
+    pass.set_param("level", 0.5).unwrap();
+    assert_eq!(rows(&mut r, 4), [100, 200, 100, 200]);
+    assert!(pass_err(&mut r, scanlines, "period", 2.5).contains("whole number"));
+    assert!(pass_err(&mut r, scanlines, "level", 3.0).contains("level must be in"));
+    assert!(pass_err(&mut r, scanlines, "radius", 1.0).contains("no parameter"));
+
+    r.post_chain_mut().get_mut(scanlines).unwrap().set_enabled(false);
+    assert_eq!(rows(&mut r, 4), [200, 200, 200, 200]);
+}
+
+fn pass_err(r: &mut Renderer, index: usize, name: &str, value: f32) -> String {
+    r.post_chain_mut().get_mut(index).unwrap().set_param(name, value).unwrap_err()
+}
+
+#[test]
+fn passes_can_be_added_removed_and_reordered() {
+    let mut r = gray_renderer();
+    // A second scanline pass darkens the same rows again
+    r.post_chain_mut().insert(usize::MAX, PostKind::Scanlines);
+    assert_eq!(rows(&mut r, 4), [154, 200, 200, 154]);
+    r.post_chain_mut().remove(5).unwrap();
+    assert!(r.post_chain_mut().remove(5).is_err());
+
+    // Glow before or after the scanlines sees a different frame
+    let mut glow_last = gray_renderer();
+    glow_last.set_glow(true);
+    glow_last.post_chain_mut().move_pass(0, usize::MAX).unwrap();
+    assert_eq!(glow_last.post_chain().passes().last().unwrap().kind(), PostKind::Glow);
+    let mut glow_first = gray_renderer();
+    glow_first.set_glow(true);
+    assert_ne!(rows(&mut glow_last, 4), rows(&mut glow_first, 4));
+    assert!(glow_last.post_chain_mut().move_pass(9, 0).is_err());
+}
+
+#[test]
+fn empty_chain_shows_the_scene_as_drawn() {
+    let mut r = gray_renderer();
+    *r.post_chain_mut() = PostChain::from_kinds(&[]);
+    r.set_render_scale(0.5);
+    assert_eq!(rows(&mut r, 4), [200, 200, 200, 200]);
+    assert!(PostKind::from_name("bloom").is_err());
+    for kind in PostKind::ALL {
+        assert_eq!(PostKind::from_name(kind.name()), Ok(kind));
+    }
+}
+
+#[test]
+fn low_end_mode_skips_expensive_passes() {
+    let mut r = gray_renderer();
+    r.set_glow(true);
+    r.set_low_end_mode(true);
+    assert!(!r.glow_enabled());
+    r.post_chain_mut().set_enabled(PostKind::Glow, true);
+    assert_eq!(rows(&mut r, 4), [176, 200, 200, 176]);
+}
+
+// One bright vertical line down the middle of a black frame
+struct Line;
+
+impl Scene for Line {
+    fn init(&mut self, _setup: &mut SceneSetup) {}
+
+    fn update(&mut self, _frame: &FrameInput) {}
+
+    fn draw(&mut self, _frame: &FrameInput, target: &mut DrawTarget) {
+        let x = target.width / 2;
+        for y in 0..target.height {
+            target.pixels[(y * target.width + x) * 4..][..3].fill(255);
+        }
+    }
+}
+
+// Red channel along the middle row with only glow in the chain
+fn glow_row(downsample: f32, iterations: f32) -> Vec<u8> {
+    let mut r = Renderer::new(64, 48);
+    r.register_scene("line", || Box::new(Line));
+    r.set_scene("line").unwrap();
+    *r.post_chain_mut() = PostChain::from_kinds(&[PostKind::Glow]);
+    let glow = r.post_chain_mut().get_mut(0).unwrap();
+    glow.set_param("downsample", downsample).unwrap();
+    glow.set_param("iterations", iterations).unwrap();
+    r.set_spectrum(&[255u8; 1024]);
+    r.render();
+    r.frame()[24 * 64 * 4..25 * 64 * 4].chunks_exact(4).map(|p| p[0]).collect()
+}
+
+#[test]
+fn glow_halo_fades_away_from_the_line_at_any_downsample() {
+    for downsample in [1.0, 2.0, 4.0] {
+        for iterations in [1.0, 3.0] {
+            let row = glow_row(downsample, iterations);
+            let label = format!("downsample {} iterations {}: {:?}", downsample, iterations, row);
+            assert_eq!(row[32], 255, "{}", label);
+            // Lit a few pixels out on both sides, fading with distance, dark at the edges
+            assert!(row[29] > 0 && row[35] > 0, "{}", label);
+            assert!(row[29] >= row[26] && row[35] >= row[38], "{}", label);
+            assert_eq!((row[0], row[63]), (0, 0), "{}", label);
+        }
+    }
+
+    let mut r = gray_renderer();
+    let glow = r.post_chain_mut().get_mut(0).unwrap();
+    assert_eq!(glow.param_names(), ["line_boost", "downsample", "iterations"]);
+    assert!(glow.set_param("downsample", 8.0).is_err());
+    assert!(glow.set_param("iterations", 1.5).is_err());
+}
@@ -0,0 +1,193 @@
+use alpine_lowend_wasm::{FaceFill, LineCap, LineMode, PostKind, Preset, PresetBank, Renderer, UpscaleFilter};
+
+#[test]
+fn capture_and_recall_round_trip() {
+    let mut r = Renderer::new(160, 120);
+    let mut bank = PresetBank::default();
+    r.set_param("speed", 2.5).unwrap();
+    r.set_sensitivity(30.0);
+    r.set_glow(true);
+    bank.store(2, Preset::capture(&r, "MINE")).unwrap();
+    assert_eq!(bank.active(), Some(2));
+
+    bank.recall(0, &mut r).unwrap();
+    assert_eq!(bank.active(), Some(0));
+    assert_eq!(r.params().speed, 0.6);
+    assert!(!r.glow_enabled());
+
+    bank.recall(2, &mut r).unwrap();
+    assert_eq!(bank.get(2).unwrap().name, "MINE");
+    assert_eq!(r.params().speed, 2.5);
+    assert_eq!(r.sensitivity(), 30.0);
+    assert!(r.glow_enabled());
+}
+
+#[test]
+fn rejects_bad_slot_and_invalid_preset() {
+    let mut r = Renderer::new(160, 120);
+    let mut bank = PresetBank::default();
+    assert!(bank.recall(4, &mut r).is_err());
+    let mut bad = Preset::capture(&r, "BAD");
+    bad.performance_mode = 7;
+    assert!(bank.store(1, bad).is_err());
+    assert_eq!(bank.active(), Some(0));
+}
+
+#[test]
+fn json_round_trip() {
+    let bank = PresetBank::default();
+    for slot in 0..4 {
+        let preset = bank.get(slot).unwrap();
+        let text = preset.to_json();
+        assert_eq!(&Preset::from_json(&text).unwrap(), preset);
+    }
+    let r = Renderer::new(160, 120);
+    let captured = Preset::capture(&r, "PHASES");
+    assert_eq!(Preset::from_json(&captured.to_json()).unwrap(), captured);
+}
+
+#[test]
+fn json_partial_document_uses_defaults() {
+    let p = Preset::from_json(r#"{"format": "alpine-preset", "version": 1, "params": {"fog_near": 0.95, "fog_far": 0.98}}"#)
+        .unwrap();
+    assert_eq!(p.params.fog_near, 0.95);
+    assert_eq!(p.params.speed, 0.6);
+    assert_eq!(p.color_phases, None);
+}
+
+#[test]
+fn json_errors_name_the_problem() {
+    let err = |text: &str| Preset::from_json(text).unwrap_err();
+    assert!(err("[1, 2]").contains("object"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 1, "colour": 1}"#).contains("\"colour\""));
+    assert!(err(r#"{"format": "alpine-preset", "version": 1, "params": {"sped": 1}}"#).contains("\"sped\""));
+    assert!(err(r#"{"format": "alpine-preset", "version": 1, "params": {"speed": 99}}"#).contains("speed must be in"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 3, "bloom": 1}"#).contains("version 3"));
+    assert!(err(r#"{"version": 1}"#).contains("format"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 1, "performance_mode": 9}"#).contains("performance_mode"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "render_scale": 2}"#).contains("render_scale"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "upscale_filter": "cubic"}"#).contains("upscale filter"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "line_mode": "wu"}"#).contains("line mode"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "line_cap": "butt"}"#).contains("line cap"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "face_fill": "gouraud"}"#).contains("face fill"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "depth_test": 1}"#).contains("depth_test"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "post": {}}"#).contains("\"post\""));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "post": [{"kind": "bloom"}]}"#).contains("\"bloom\""));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "post": [{"kind": "glow", "on": true}]}"#).contains("\"on\""));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "post": [{"kind": "glow", "enabled": 1}]}"#).contains("enabled"));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "post": [{"kind": "glow", "params": {"radius": 2}}]}"#).contains("\"radius\""));
+    assert!(err(r#"{"format": "alpine-preset", "version": 2, "post": [{"kind": "glow", "params": {"downsample": 9}}]}"#).contains("downsample"));
+}
+
+#[test]
+fn render_settings_round_trip_and_stay_when_absent() {
+    let mut r = Renderer::new(160, 120);
+    r.set_render_scale(0.5);
+    r.set_upscale_filter(UpscaleFilter::Nearest);
+    r.set_line_mode(LineMode::SmoothMax);
+    r.set_line_cap(LineCap::Square);
+    r.set_face_fill(FaceFill::Depth);
+    r.set_depth_test(true);
+    let preset = Preset::capture(&r, "LOOK");
+    assert_eq!(Preset::from_json(&preset.to_json()).unwrap(), preset);
+
+    let mut other = Renderer::new(160, 120);
+    preset.apply(&mut other).unwrap();
+    let same = |o: &Renderer| {
+        assert_eq!(o.render_scale(), 0.5);
+        assert_eq!(o.upscale_filter(), UpscaleFilter::Nearest);
+        assert_eq!(o.line_mode(), LineMode::SmoothMax);
+        assert_eq!(o.line_cap(), LineCap::Square);
+        assert_eq!(o.face_fill(), FaceFill::Depth);
+        assert!(o.depth_test_enabled());
+    };
+    same(&other);
+    // A document from before these keys existed keeps whatever is showing
+    Preset::from_json(r#"{"format": "alpine-preset", "version": 1}"#).unwrap().apply(&mut other).unwrap();
+    same(&other);
+}
+
+#[test]
+fn scene_is_kept_unless_the_preset_switches_it() {
+    let mut r = Renderer::new(160, 120);
+    let preset = Preset::capture(&r, "TUNNEL");
+    assert_eq!(preset.scene.as_deref(), Some("tunnel"));
+    assert_eq!(Preset::from_json(&preset.to_json()).unwrap(), preset);
+
+    // Naming the active scene does not restart it: the frames match a
+    // renderer that never saw the preset (which leaves the colors alone here)
+    let mut keep = preset.clone();
+    keep.color_phases = None;
+    let mut plain = Renderer::new(160, 120);
+    for i in 0..3 {
+        if i == 1 {
+            keep.apply(&mut r).unwrap();
+        }
+        for x in [&mut r, &mut plain] {
+            x.set_spectrum(&[150u8; 1024]);
+            x.render();
+        }
+    }
+    assert!(r.frame() == plain.frame());
+
+    let mut bad = preset.clone();
+    bad.scene = Some("cube".into());
+    bad.params.speed = 3.0;
+    assert!(bad.apply(&mut r).is_err());
+    assert_eq!(r.params().speed, preset.params.speed);
+}
+
+#[test]
+fn post_chain_round_trips_in_order() {
+    let mut r = Renderer::new(160, 120);
+    let chain = r.post_chain_mut();
+    chain.move_pass(3, 0).unwrap();
+    chain.get_mut(0).unwrap().set_param("period", 5.0).unwrap();
+    chain.get_mut(1).unwrap().set_enabled(true);
+    chain.remove(4).unwrap();
+    let preset = Preset::capture(&r, "CHAIN");
+    assert_eq!(Preset::from_json(&preset.to_json()).unwrap(), preset);
+
+    let kinds = |r: &Renderer| r.post_chain().passes().iter().map(|p| (p.kind(), p.enabled())).collect::<Vec<_>>();
+    let mut other = Renderer::new(160, 120);
+    preset.apply(&mut other).unwrap();
+    assert_eq!(kinds(&other), kinds(&r));
+    assert_eq!(other.post_chain().passes()[0].param("period"), Some(5.0));
+    assert!(other.glow_enabled());
+
+    // Passes and params left out of the document take their defaults; a
+    // preset without "post" keeps the chain
+    let doc = r#"{"format": "alpine-preset", "version": 2, "glow": true,
+        "post": [{"kind": "invert"}, {"kind": "glow", "enabled": false, "params": {"iterations": 3}}]}"#;
+    Preset::from_json(doc).unwrap().apply(&mut other).unwrap();
+    assert_eq!(kinds(&other), [(PostKind::Invert, true), (PostKind::Glow, false)]);
+    let glow = &other.post_chain().passes()[1];
+    assert_eq!((glow.param("iterations"), glow.param("downsample")), (Some(3.0), Some(2.0)));
+    Preset::from_json(r#"{"format": "alpine-preset", "version": 1}"#).unwrap().apply(&mut other).unwrap();
+    assert_eq!(other.post_chain().len(), 2);
+}
+
+#[test]
+fn version_1_documents_still_load() {
+    // As the first preset format wrote them, before the render, scene, palette and post keys
+    let doc = r#"{
+        "format": "alpine-preset", "version": 1, "name": "OLD", "sensitivity": 12,
+        "performance_mode": 1, "glow": true, "smoothing": false,
+        "color_phases": [0.1, 0.2, 0.3, 0.4, 0.5, 0.6], "params": {"speed": 1.2}
+    }"#;
+    let p = Preset::from_json(doc).unwrap();
+    assert_eq!((p.name.as_str(), p.sensitivity, p.performance_mode, p.glow), ("OLD", 12.0, 1, true));
+    assert_eq!(p.params.speed, 1.2);
+    assert_eq!(p.color_phases, Some([0.1, 0.2, 0.3, 0.4, 0.5, 0.6]));
+    assert_eq!((p.render_scale, p.scene.as_deref(), p.post.as_ref()), (None, None, None));
+
+    let mut r = Renderer::new(160, 120);
+    r.set_line_mode(LineMode::SmoothMax);
+    p.apply(&mut r).unwrap();
+    assert_eq!(r.line_mode(), LineMode::SmoothMax);
+    assert!(r.glow_enabled());
+
+    let newer = r#"{"format": "alpine-preset", "version": 1, "scene": "tunnel"}"#;
+    assert!(Preset::from_json(newer).unwrap_err().contains("needs version 2"));
+    assert!(Preset::capture(&r, "NEW").to_json().contains("\"version\": 2"));
+}
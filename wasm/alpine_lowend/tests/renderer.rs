@@ -0,0 +1,272 @@
+use alpine_lowend_wasm::raster::{draw_line, fill_convex, Canvas};
+use alpine_lowend_wasm::{FaceFill, LineCap, LineMode, Renderer, UpscaleFilter};
+
+fn render_frames(spectrum: &[u8], frames: usize) -> Vec<u8> {
+    let mut r = Renderer::new(160, 120);
+    for _ in 0..frames {
+        r.set_spectrum(spectrum);
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+#[test]
+fn frame_is_rgba_sized_and_opaque() {
+    let frame = render_frames(&[0u8; 1024], 1);
+    assert_eq!(frame.len(), 160 * 120 * 4);
+    assert!(frame.chunks_exact(4).all(|p| p[3] == 255));
+}
+
+#[test]
+fn spectrum_changes_output() {
+    let silent = render_frames(&[0u8; 1024], 10);
+    let loud = render_frames(&[200u8; 1024], 10);
+    assert!(loud.chunks_exact(4).any(|p| p[0] > 0 || p[1] > 0 || p[2] > 0));
+    assert_ne!(silent, loud);
+}
+
+#[test]
+fn same_inputs_render_same_frames() {
+    let a = render_frames(&[120u8; 1024], 5);
+    let b = render_frames(&[120u8; 1024], 5);
+    assert_eq!(a, b);
+}
+
+fn render_seeded(seed: u64) -> Vec<u8> {
+    let mut r = Renderer::new(160, 120);
+    r.set_seed(seed);
+    let spectrum: Vec<u8> = (0..1024).map(|i| (i * 7 % 256) as u8).collect();
+    for _ in 0..5 {
+        r.set_spectrum(&spectrum);
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+#[test]
+fn seed_decides_random_choices() {
+    assert_eq!(render_seeded(7), render_seeded(7));
+    assert_ne!(render_seeded(7), render_seeded(8));
+    let mut r = Renderer::new(160, 120);
+    r.set_seed(7);
+    assert_eq!(r.seed(), 7);
+}
+
+#[test]
+fn zero_sized_renderer_is_a_no_op() {
+    let mut r = Renderer::new(0, 0);
+    r.set_spectrum(&[255u8; 16]);
+    r.render();
+    assert!(r.frame().is_empty());
+}
+
+fn render_scaled(scale: f32, filter: UpscaleFilter) -> Vec<u8> {
+    let mut r = Renderer::new(160, 120);
+    r.set_render_scale(scale);
+    r.set_upscale_filter(filter);
+    for _ in 0..5 {
+        r.set_spectrum(&[180u8; 1024]);
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+#[test]
+fn render_scale_keeps_output_size() {
+    let full = render_scaled(1.0, UpscaleFilter::Bilinear);
+    for filter in [UpscaleFilter::Nearest, UpscaleFilter::Bilinear] {
+        let half_res = render_scaled(0.5, filter);
+        assert_eq!(half_res.len(), full.len());
+        assert!(half_res.chunks_exact(4).all(|p| p[3] == 255));
+        assert!(half_res.chunks_exact(4).any(|p| p[0] > 0 || p[1] > 0 || p[2] > 0));
+        assert_ne!(half_res, full);
+    }
+}
+
+#[test]
+fn nearest_upscale_duplicates_internal_pixels() {
+    let mut r = Renderer::new(160, 120);
+    r.set_render_scale(0.5);
+    assert_eq!(r.internal_size(), (80, 60));
+    let frame = render_scaled(0.5, UpscaleFilter::Nearest);
+    // Horizontal neighbours in each 2-pixel column pair come from one source pixel;
+    // rows differ only where scanlines darken every third row
+    for y in 0..120 {
+        for x in (0..160).step_by(2) {
+            let i = (y * 160 + x) * 4;
+            assert_eq!(frame[i..i + 4], frame[i + 4..i + 8]);
+        }
+    }
+}
+
+#[test]
+fn frame_skip_renders_every_other_call() {
+    let mut r = Renderer::new(160, 120);
+    r.set_frame_skip(1);
+    r.set_spectrum(&[180u8; 1024]);
+    r.render();
+    let first = r.frame().to_vec();
+    r.render();
+    assert_eq!(r.frame(), &first[..]);
+    r.render();
+    assert_ne!(r.frame(), &first[..]);
+}
+
+#[test]
+fn pointer_steers_and_returns_to_center() {
+    let run = |pointer: &dyn Fn(usize) -> bool, inertia: f32| {
+        let mut r = Renderer::new(160, 120);
+        r.set_param("steer_inertia", inertia).unwrap();
+        (0..8)
+            .map(|f| {
+                r.set_mouse(150.0, 110.0, pointer(f), false);
+                r.set_spectrum(&[150u8; 1024]);
+                r.render();
+                r.frame().to_vec()
+            })
+            .collect::<Vec<_>>()
+    };
+    let still = run(&|_| false, 0.0);
+    let snappy = run(&|f| f < 3, 0.0);
+    assert_ne!(snappy[2], still[2]);
+    // With no inertia the center is back at rest as soon as the pointer leaves
+    assert_eq!(snappy[3..], still[3..]);
+    let eased = run(&|f| f < 3, 0.9);
+    assert_ne!(eased[3], still[3]);
+}
+
+// Renders `calls` frames of a steady spectrum, each `interval` seconds apart
+fn render_timed(calls: u32, interval: f32, level: u8) -> Renderer {
+    let mut r = Renderer::new(160, 120);
+    r.set_frame_interval(interval);
+    for _ in 0..calls {
+        r.set_spectrum(&[level; 1024]);
+        r.render();
+    }
+    r
+}
+
+fn mean_difference(a: &[u8], b: &[u8]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x.abs_diff(*y) as f32).sum::<f32>() / a.len() as f32
+}
+
+#[test]
+fn animation_speed_follows_time_not_calls() {
+    for level in [0u8, 150] {
+        let at_60 = render_timed(60, 1.0 / 60.0, level);
+        let at_144 = render_timed(144, 1.0 / 144.0, level);
+        // What 144 Hz looked like when every call was a 60 Hz step
+        let per_call = render_timed(144, 1.0 / 60.0, level);
+        for (a, b) in at_60.color_phases().iter().zip(at_144.color_phases()) {
+            assert!((a - b).abs() < 1e-3, "{} vs {}", a, b);
+        }
+        let same_time = mean_difference(at_60.frame(), at_144.frame());
+        let same_calls = mean_difference(at_60.frame(), per_call.frame());
+        assert!(same_time * 2.0 < same_calls, "{} vs {}", same_time, same_calls);
+    }
+}
+
+// Five frames of a steady spectrum with `setup` applied after the low-end switch
+fn render_with(low_end: bool, setup: impl FnOnce(&mut Renderer)) -> Vec<u8> {
+    let mut r = Renderer::new(160, 120);
+    r.set_low_end_mode(low_end);
+    setup(&mut r);
+    for _ in 0..5 {
+        r.set_spectrum(&[150u8; 1024]);
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+// Pixels that are not black
+fn lit(frame: &[u8]) -> usize {
+    frame.chunks_exact(4).filter(|p| p[..3] != [0, 0, 0]).count()
+}
+
+#[test]
+fn smooth_lines_cover_more_pixels_and_low_end_stays_aliased() {
+    let lines = |mode: LineMode, low_end: bool| render_with(low_end, |r| r.set_line_mode(mode));
+    let aliased = lines(LineMode::Aliased, false);
+    let add = lines(LineMode::SmoothAdd, false);
+    let max = lines(LineMode::SmoothMax, false);
+    assert_ne!(add, max);
+    // Coverage spills onto the neighbouring pixel along the whole line
+    assert!(lit(&max) > lit(&aliased));
+    assert_eq!(lines(LineMode::SmoothMax, true), lines(LineMode::Aliased, true));
+    for mode in [LineMode::Aliased, LineMode::SmoothAdd, LineMode::SmoothMax] {
+        assert_eq!(LineMode::from_name(mode.name()), Ok(mode));
+    }
+    assert!(LineMode::from_name("wu").is_err());
+}
+
+#[test]
+fn fractional_line_width_and_caps() {
+    let wide = |thickness: f32, cap: LineCap| {
+        render_with(false, |r| {
+            r.set_param("mesh_thickness", thickness).unwrap();
+            r.set_line_cap(cap);
+        })
+    };
+    let thin = lit(&wide(1.0, LineCap::Round));
+    let medium = lit(&wide(2.5, LineCap::Round));
+    let thick = lit(&wide(4.0, LineCap::Round));
+    assert!(thin < medium && medium < thick, "{} {} {}", thin, medium, thick);
+    assert_ne!(wide(4.0, LineCap::Round), wide(4.0, LineCap::Square));
+    for cap in [LineCap::Round, LineCap::Square] {
+        assert_eq!(LineCap::from_name(cap.name()), Ok(cap));
+    }
+}
+
+#[test]
+fn filled_faces_cover_the_gaps_between_lines() {
+    let faces = |fill: FaceFill, low_end: bool| render_with(low_end, |r| r.set_face_fill(fill));
+    let wire = faces(FaceFill::None, false);
+    let flat = faces(FaceFill::Flat, false);
+    let depth = faces(FaceFill::Depth, false);
+    assert!(lit(&flat) > lit(&wire));
+    assert_ne!(flat, depth);
+    assert_eq!(faces(FaceFill::Depth, true), faces(FaceFill::None, true));
+    for fill in [FaceFill::None, FaceFill::Flat, FaceFill::Depth] {
+        assert_eq!(FaceFill::from_name(fill.name()), Ok(fill));
+    }
+}
+
+#[test]
+fn depth_test_hides_farther_geometry() {
+    let depth = |on: bool, low_end: bool| {
+        render_with(low_end, |r| {
+            r.set_face_fill(FaceFill::Flat);
+            r.set_depth_test(on);
+        })
+    };
+    assert_ne!(depth(true, false), depth(false, false));
+
+    // A near and a far quad overlapping in the middle: the near one must win
+    // whichever is drawn first, and a far line must not show through it
+    let quad = |x: f32, z: f32| [(x, 2.0, z), (x + 8.0, 2.0, z), (x + 8.0, 10.0, z), (x, 10.0, z)];
+    let (near, far) = (quad(2.0, 1.0), quad(6.0, 5.0));
+    for near_first in [true, false] {
+        let (mut buf, mut zbuf) = (vec![0u8; 16 * 12 * 4], vec![f32::INFINITY; 16 * 12]);
+        let mut c = Canvas::bands(&mut buf, Some(&mut zbuf), 16, 12, 12).pop().unwrap();
+        let mut draw = |pts: &[(f32, f32, f32)], color: u8| fill_convex(&mut c, pts, color, 0, 0, 1.0);
+        if near_first {
+            draw(&near, 200);
+            draw(&far, 100);
+        } else {
+            draw(&far, 100);
+            draw(&near, 200);
+        }
+        draw_line(&mut c, 0, 6, 9.0, 15, 6, 9.0, 0, 0, 255);
+        let at = |x: usize, y: usize| &buf[(y * 16 + x) * 4..(y * 16 + x) * 4 + 3];
+        assert_eq!(at(8, 4), [200, 0, 0], "overlap, near first: {}", near_first);
+        assert_eq!(at(13, 4), [100, 0, 0], "far quad alone");
+        assert_eq!(at(8, 6), [200, 0, 0], "far line over the near quad");
+        assert_eq!(at(15, 6), [0, 0, 255], "far line over nothing");
+    }
+    assert_eq!(depth(true, true), depth(false, true));
+    let mut r = Renderer::new(160, 120);
+    r.set_depth_test(true);
+    assert!(r.depth_test_enabled());
+    r.set_depth_test(false);
+    assert!(!r.depth_test_enabled());
+}
@@ -0,0 +1,73 @@
+use alpine_lowend_wasm::{DrawTarget, FrameInput, Renderer, Scene, SceneSetup};
+
+// Fills the whole target with one gray level that follows the bass
+#[derive(Default)]
+struct Flat {
+    level: u8,
+    inits: u32,
+}
+
+impl Scene for Flat {
+    fn init(&mut self, _setup: &mut SceneSetup) {
+        self.inits += 1;
+    }
+
+    fn update(&mut self, frame: &FrameInput) {
+        self.level = (frame.audio.bass * 200.0) as u8 + self.inits as u8;
+    }
+
+    fn draw(&mut self, _frame: &FrameInput, target: &mut DrawTarget) {
+        for px in target.pixels.chunks_exact_mut(4) {
+            px[..3].fill(self.level);
+        }
+    }
+}
+
+fn render(r: &mut Renderer, level: u8) -> Vec<u8> {
+    r.set_spectrum(&[level; 1024]);
+    r.render();
+    r.frame().to_vec()
+}
+
+#[test]
+fn tunnel_is_the_default_scene() {
+    let mut r = Renderer::new(160, 120);
+    assert_eq!(r.scene_name(), "tunnel");
+    assert_eq!(r.scene_names(), ["tunnel"]);
+    let err = r.set_scene("plasma").unwrap_err();
+    assert!(err.contains("plasma") && err.contains("tunnel"), "{}", err);
+    assert_eq!(r.scene_name(), "tunnel");
+}
+
+#[test]
+fn registered_scene_draws_under_the_shared_passes() {
+    let mut r = Renderer::new(160, 120);
+    r.register_scene("flat", || Box::new(Flat::default()));
+    r.set_scene("flat").unwrap();
+    assert_eq!(r.scene_name(), "flat");
+    let frame = render(&mut r, 255);
+    // Initialized once on switching; scanlines darken every third row at output size
+    assert_eq!(&frame[..4], &[176, 176, 176, 255]);
+    assert_eq!(&frame[160 * 4..160 * 4 + 4], &[201, 201, 201, 255]);
+
+    // Holding the pointer down inverts whatever the scene drew
+    r.set_mouse(0.0, 0.0, false, true);
+    for _ in 0..60 {
+        render(&mut r, 255);
+    }
+    assert!(r.frame()[160 * 4] < 100);
+}
+
+#[test]
+fn switching_back_rebuilds_the_tunnel() {
+    let mut r = Renderer::new(160, 120);
+    r.register_scene("flat", || Box::new(Flat::default()));
+    r.set_scene("flat").unwrap();
+    let flat = render(&mut r, 150);
+    r.set_scene("tunnel").unwrap();
+    let tunnel = (0..5).map(|_| render(&mut r, 150)).last().unwrap();
+    assert_ne!(tunnel, flat);
+    // Lines over a black background, not the flat gray
+    assert!(tunnel.chunks_exact(4).any(|p| p[..3] == [0, 0, 0]));
+    assert!(tunnel.chunks_exact(4).any(|p| p[..3] != [0, 0, 0]));
+}
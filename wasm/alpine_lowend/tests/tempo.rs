@@ -0,0 +1,74 @@
+use alpine_lowend_wasm::TempoTracker;
+
+const FPS: f32 = 60.0;
+
+// Spectrum frames with a decaying low-end hit every beat over a steady bed
+fn kick_pattern(bpm: f32, seconds: f32) -> Vec<Vec<u8>> {
+    let frames = (seconds * FPS) as usize;
+    let period = 60.0 / bpm;
+    let mut level = 0.0f32;
+    (0..frames)
+        .map(|f| {
+            let t = f as f32 / FPS;
+            let prev_t = t - 1.0 / FPS;
+            if f == 0 || (t / period).floor() != (prev_t / period).floor() {
+                level = 1.0;
+            }
+            level *= 0.8;
+            (0..128).map(|bin| if bin < 16 { (60.0 + 190.0 * level) as u8 } else { 50 }).collect()
+        })
+        .collect()
+}
+
+fn track(frames: &[Vec<u8>]) -> TempoTracker {
+    let mut t = TempoTracker::default();
+    for f in frames {
+        t.push(f, 1.0 / FPS);
+    }
+    t
+}
+
+#[test]
+fn finds_steady_tempos() {
+    // A bare kick train at 174 is as much 87 as 174; the prior resolves such
+    // pairs toward 120, so only tempos without a closer octave are checked
+    for bpm in [90.0, 120.0, 128.0, 150.0] {
+        let t = track(&kick_pattern(bpm, 12.0));
+        assert!((t.bpm() - bpm).abs() < bpm * 0.03, "expected {} got {}", bpm, t.bpm());
+        assert!(t.confidence() > 0.3, "confidence {} at {}", t.confidence(), bpm);
+    }
+}
+
+#[test]
+fn phase_is_near_zero_just_after_a_beat() {
+    // 12 s at 120 BPM ends exactly on a beat boundary; one more frame lands on the hit
+    let mut frames = kick_pattern(120.0, 12.0 + 1.0 / FPS);
+    frames.truncate((12.0 * FPS) as usize + 1);
+    let t = track(&frames);
+    let phase = t.beat_phase();
+    let distance = phase.min(1.0 - phase);
+    assert!(distance < 0.15, "phase {}", phase);
+}
+
+#[test]
+fn silence_has_no_tempo() {
+    let t = track(&vec![vec![0u8; 128]; 600]);
+    assert_eq!(t.bpm(), 0.0);
+    assert_eq!(t.confidence(), 0.0);
+}
+
+#[test]
+fn irregular_hits_have_low_confidence() {
+    // Hits at pseudo-random gaps
+    let mut frames = Vec::new();
+    let mut state = 12345u32;
+    while frames.len() < 720 {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        let gap = 8 + (state >> 16) as usize % 40;
+        frames.push(vec![240u8; 128]);
+        frames.extend(std::iter::repeat_n(vec![40u8; 128], gap));
+    }
+    let steady = track(&kick_pattern(120.0, 12.0));
+    let irregular = track(&frames);
+    assert!(irregular.confidence() < steady.confidence());
+}
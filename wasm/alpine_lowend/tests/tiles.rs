@@ -0,0 +1,55 @@
+use alpine_lowend_wasm::{tiles, FaceFill, LineMode, Renderer, UpscaleFilter};
+
+// A varied spectrum, so displaced faces and wide audio lines cross band edges
+fn render(setup: impl Fn(&mut Renderer), tile_rows: usize) -> Vec<u8> {
+    let mut r = Renderer::new(160, 120);
+    setup(&mut r);
+    r.set_tile_rows(tile_rows);
+    let spectrum: Vec<u8> = (0..1024).map(|i| (i * 13 % 256) as u8).collect();
+    for _ in 0..12 {
+        r.set_spectrum(&spectrum);
+        r.render();
+    }
+    r.frame().to_vec()
+}
+
+#[test]
+fn band_size_does_not_change_the_frame() {
+    let setups: [fn(&mut Renderer); 4] = [
+        |_| {},
+        |r| {
+            r.set_line_mode(LineMode::SmoothAdd);
+            r.set_face_fill(FaceFill::Depth);
+            r.set_depth_test(true);
+        },
+        |r| {
+            r.set_performance_mode(1);
+            r.set_glow(true);
+            r.set_render_scale(0.5);
+        },
+        |r| {
+            r.set_line_mode(LineMode::SmoothMax);
+            r.set_render_scale(0.75);
+            r.set_upscale_filter(UpscaleFilter::Nearest);
+        },
+    ];
+    for (i, setup) in setups.iter().enumerate() {
+        let whole = render(setup, 0);
+        for rows in [1, 7, 32, 500] {
+            assert!(render(setup, rows) == whole, "setup {} differs with {}-row bands", i, rows);
+        }
+    }
+}
+
+#[test]
+fn band_rows_cover_the_frame() {
+    assert_eq!(tiles::band_rows(120, 7), 7);
+    assert_eq!(tiles::band_rows(0, 0), 1);
+    if tiles::threads() == 1 {
+        assert_eq!(tiles::band_rows(120, 0), 120);
+    } else {
+        assert!((16..=120).contains(&tiles::band_rows(120, 0)));
+    }
+    let r = Renderer::new(16, 12);
+    assert_eq!(r.tile_rows(), 0);
+}
@@ -0,0 +1,67 @@
+use alpine_lowend_wasm::{DrumKind, TransientDetector};
+
+const DT: f32 = 1.0 / 60.0;
+
+// 128 bins over 0-24 kHz: kick energy in bin 0, snare in 2..20, hats in 40..80
+fn frame(kick: bool, snare: bool, hat: bool) -> Vec<u8> {
+    (0..128)
+        .map(|bin| match bin {
+            0 if kick => 240,
+            2..=20 if snare => 200,
+            40..=80 if hat => 180,
+            _ => 40,
+        })
+        .collect()
+}
+
+fn count(d: &mut TransientDetector, frames: &[Vec<u8>]) -> [usize; 3] {
+    let mut counts = [0; 3];
+    for f in frames {
+        for hit in d.push(f, DT) {
+            counts[DrumKind::ALL.iter().position(|&k| k == hit.kind).unwrap()] += 1;
+            assert!(hit.strength > 0.0 && hit.strength <= 1.0);
+        }
+    }
+    counts
+}
+
+#[test]
+fn separates_kick_snare_and_hat() {
+    // Kick on every beat (30 frames), snare on the off-beat, hats every 15 frames
+    let frames: Vec<Vec<u8>> = (0..600).map(|f| frame(f % 30 == 0, f % 30 == 15, f % 15 == 7)).collect();
+    let mut d = TransientDetector::default();
+    let [kicks, snares, hats] = count(&mut d, &frames);
+    assert!((19..=21).contains(&kicks), "{} kicks", kicks);
+    assert!((19..=21).contains(&snares), "{} snares", snares);
+    assert!((39..=41).contains(&hats), "{} hats", hats);
+}
+
+#[test]
+fn refractory_period_merges_close_hits() {
+    // Two kick onsets two frames (33 ms) apart count once
+    let mut frames = vec![frame(false, false, false); 60];
+    frames[30] = frame(true, false, false);
+    frames[32] = frame(true, false, false);
+    let mut d = TransientDetector::default();
+    assert_eq!(count(&mut d, &frames)[0], 1);
+}
+
+#[test]
+fn steady_signal_triggers_nothing() {
+    let frames = vec![frame(true, true, true); 300];
+    let mut d = TransientDetector::default();
+    assert_eq!(count(&mut d, &frames), [0, 0, 0]);
+}
+
+#[test]
+fn drain_returns_pending_hits_with_timestamps() {
+    let mut d = TransientDetector::default();
+    for f in 0..120 {
+        d.push(&frame(f == 60, false, false), DT);
+    }
+    let hits: Vec<_> = d.drain().collect();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].kind, DrumKind::Kick);
+    assert!((hits[0].time - 61.0 * DT).abs() < 1e-3);
+    assert_eq!(d.drain().count(), 0);
+}